@@ -1,8 +1,7 @@
 use scenic::prelude::*;
-use scenic::scanlines::{LineEvent, ScanState};
-use std::collections::BTreeMap;
+use scenic::scanlines::ScanState;
 
-pub fn main() {
+pub fn main() -> Result<(), Error> {
     let p1 = Polygon::new(
         vec![
             Point::new(5., 10.),
@@ -36,43 +35,30 @@ pub fn main() {
     );
 
     let mut scene = Scene::new();
-    scene.add_poly(p1);
-    scene.add_poly(p2);
-    scene.add_poly(p3);
-    scene.add_poly(p4);
+    scene.add_poly(p1)?;
+    scene.add_poly(p2)?;
+    scene.add_poly(p3)?;
+    scene.add_poly(p4)?;
 
     let mut scan_state = ScanState::new(&scene);
-    let mut final_lines: Vec<Line> = Vec::new();
-    let mut cur_lines: BTreeMap<&Line, Point> = BTreeMap::new();
 
     let mut i = 1;
-    loop {
-        let lines = scan_state.step();
-        let pointer = scan_state.cursor.unwrap();
+    while !scan_state.done() {
+        scan_state.step();
 
         let mut d = DebugDraw::new();
-
-        for (line, line_event) in lines {
-            match line_event {
-                LineEvent::Begin => {
-                    cur_lines.insert(line, pointer);
-                }
-                LineEvent::End => {
-                    if let Some(from_point) = cur_lines.get(line) {
-                        final_lines.push(Line::new(*from_point, pointer));
-                        cur_lines.remove(line);
-                    }
-                }
-            }
-        }
-
+        d.add_scene(&scene);
         d.add_scan_state(&scan_state);
-
-        for line in &final_lines {
-            d.add_line(line).stroke("black");
-        }
-
-        d.save(&format!("step_{:0>3}.svg", i));
+        d.save(&format!("step_{:0>3}.svg", i))?;
         i += 1;
     }
+
+    let mut d = DebugDraw::new();
+    d.add_scene(&scene);
+    for line in &scene.visible_lines() {
+        d.add_line(line).stroke("black");
+    }
+    d.save("visible.svg")?;
+
+    Ok(())
 }
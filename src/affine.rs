@@ -0,0 +1,184 @@
+use crate::point::Point;
+use std::ops::Mul;
+
+/// An affine transformation of the plane, mapping `(x, y)` to
+/// `(a * x + c * y + e, b * x + d * y + f)`. The coefficients are in the same
+/// order as SVG's `matrix(a b c d e f)`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Affine2 {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub e: f64,
+    pub f: f64,
+}
+
+impl Affine2 {
+    pub fn new(a: f64, b: f64, c: f64, d: f64, e: f64, f: f64) -> Affine2 {
+        Affine2 { a, b, c, d, e, f }
+    }
+
+    /// The transformation which leaves every point where it is.
+    pub fn identity() -> Affine2 {
+        Affine2::new(1., 0., 0., 1., 0., 0.)
+    }
+
+    pub fn translate(dx: f64, dy: f64) -> Affine2 {
+        Affine2::new(1., 0., 0., 1., dx, dy)
+    }
+
+    /// Scale about the origin. A negative factor reflects across an axis.
+    pub fn scale(sx: f64, sy: f64) -> Affine2 {
+        Affine2::new(sx, 0., 0., sy, 0., 0.)
+    }
+
+    /// Rotate counterclockwise by `angle` radians about the origin.
+    pub fn rotate(angle: f64) -> Affine2 {
+        let (sin, cos) = angle.sin_cos();
+        Affine2::new(cos, sin, -sin, cos, 0., 0.)
+    }
+
+    /// Rotate counterclockwise by `angle` radians about `origin`.
+    pub fn rotate_about(origin: Point, angle: f64) -> Affine2 {
+        Affine2::translate(-origin.x, -origin.y)
+            .then(&Affine2::rotate(angle))
+            .then(&Affine2::translate(origin.x, origin.y))
+    }
+
+    /// Skew along the x axis by `angle` radians, moving each point in x by
+    /// `tan(angle)` times its y.
+    pub fn skew_x(angle: f64) -> Affine2 {
+        Affine2::new(1., 0., angle.tan(), 1., 0., 0.)
+    }
+
+    /// Skew along the y axis by `angle` radians, moving each point in y by
+    /// `tan(angle)` times its x.
+    pub fn skew_y(angle: f64) -> Affine2 {
+        Affine2::new(1., angle.tan(), 0., 1., 0., 0.)
+    }
+
+    /// Returns the determinant of the linear part. It is negative if the
+    /// transformation is a reflection, and zero if it collapses the plane
+    /// onto a line or point.
+    pub fn determinant(&self) -> f64 {
+        self.a * self.d - self.b * self.c
+    }
+
+    /// Returns the transformation which applies this one and then `next`.
+    pub fn then(&self, next: &Affine2) -> Affine2 {
+        *next * *self
+    }
+
+    /// Returns the transformation which undoes this one, or `None` if it is
+    /// not invertible.
+    pub fn inverse(&self) -> Option<Affine2> {
+        let det = self.determinant();
+        if det == 0. || !det.is_finite() {
+            return None;
+        }
+
+        let a = self.d / det;
+        let b = -self.b / det;
+        let c = -self.c / det;
+        let d = self.a / det;
+        Some(Affine2::new(
+            a,
+            b,
+            c,
+            d,
+            -(a * self.e + c * self.f),
+            -(b * self.e + d * self.f),
+        ))
+    }
+
+    /// Returns the image of `point` under this transformation.
+    pub fn apply(&self, point: Point) -> Point {
+        Point::new(
+            self.a * point.x + self.c * point.y + self.e,
+            self.b * point.x + self.d * point.y + self.f,
+        )
+    }
+}
+
+impl Default for Affine2 {
+    fn default() -> Affine2 {
+        Affine2::identity()
+    }
+}
+
+/// Composition, as for matrices: `(t1 * t2).apply(p)` is `t1.apply(t2.apply(p))`.
+impl Mul for Affine2 {
+    type Output = Affine2;
+
+    fn mul(self, other: Affine2) -> Affine2 {
+        Affine2::new(
+            self.a * other.a + self.c * other.b,
+            self.b * other.a + self.d * other.b,
+            self.a * other.c + self.c * other.d,
+            self.b * other.c + self.d * other.d,
+            self.a * other.e + self.c * other.f + self.e,
+            self.b * other.e + self.d * other.f + self.f,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::FRAC_PI_2;
+
+    #[test]
+    fn test_apply() {
+        let p = Point::new(2., 3.);
+
+        assert_eq!(p, Affine2::identity().apply(p));
+        assert_eq!(Point::new(3., 1.), Affine2::translate(1., -2.).apply(p));
+        assert_eq!(Point::new(4., -3.), Affine2::scale(2., -1.).apply(p));
+        assert!(Affine2::rotate(FRAC_PI_2)
+            .apply(p)
+            .approx_eq(Point::new(-3., 2.), 1e-12));
+        assert!(Affine2::rotate_about(Point::new(1., 1.), FRAC_PI_2)
+            .apply(p)
+            .approx_eq(Point::new(-1., 2.), 1e-12));
+        assert!(Affine2::skew_x(std::f64::consts::FRAC_PI_4)
+            .apply(p)
+            .approx_eq(Point::new(5., 3.), 1e-12));
+        assert!(Affine2::skew_y(std::f64::consts::FRAC_PI_4)
+            .apply(p)
+            .approx_eq(Point::new(2., 5.), 1e-12));
+    }
+
+    #[test]
+    fn test_composition() {
+        let p = Point::new(2., 3.);
+        let t1 = Affine2::translate(1., 2.);
+        let t2 = Affine2::scale(3., 4.);
+
+        assert_eq!(t2.apply(t1.apply(p)), t1.then(&t2).apply(p));
+        assert_eq!(t1.apply(t2.apply(p)), (t1 * t2).apply(p));
+        assert_ne!(t1.then(&t2), t2.then(&t1));
+    }
+
+    #[test]
+    fn test_inverse() {
+        let t = Affine2::translate(1., 2.)
+            .then(&Affine2::rotate(0.3))
+            .then(&Affine2::scale(2., -0.5))
+            .then(&Affine2::skew_x(0.2));
+        let inverse = t.inverse().unwrap();
+
+        let p = Point::new(-4., 7.);
+        assert!(inverse.apply(t.apply(p)).approx_eq(p, 1e-12));
+        assert!(t.apply(inverse.apply(p)).approx_eq(p, 1e-12));
+
+        assert_eq!(None, Affine2::scale(1., 0.).inverse());
+    }
+
+    #[test]
+    fn test_determinant() {
+        assert_eq!(1., Affine2::translate(3., 4.).determinant());
+        assert_eq!(-2., Affine2::scale(-1., 2.).determinant());
+        assert!((Affine2::rotate(0.7).determinant() - 1.).abs() < 1e-12);
+    }
+}
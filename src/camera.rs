@@ -0,0 +1,238 @@
+use crate::error::Error;
+use crate::point::Point;
+use crate::point3::Point3;
+
+/// How a camera maps the space in front of it onto the image plane.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    /// Points further away appear closer to the centre of the image. `fov_y` is
+    /// the vertical field of view, in radians.
+    Perspective { fov_y: f64 },
+    /// Points are projected straight onto the image plane. `height` is the
+    /// height of the region of space which is in view.
+    Orthographic { height: f64 },
+}
+
+/// A camera which projects points in space onto a 2D image.
+///
+/// Image coordinates have their origin at the centre of the view, x to the
+/// right and y up, scaled so that the height of the view runs from -1 to 1.
+/// Each projected point is also given a depth, which is larger the closer the
+/// point is to the camera so that it can be used as a `Polygon::z`: the
+/// reciprocal of its distance in front of the camera for a perspective
+/// projection, and the negated distance for an orthographic one. Either way,
+/// depth varies linearly across the image of a planar face.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    eye: Point3,
+    right: Point3,
+    up: Point3,
+    forward: Point3,
+    projection: Projection,
+    near: f64,
+}
+
+impl Camera {
+    /// Construct a camera at `eye` looking towards `target`, turned so that
+    /// `up` points up in the image. Returns an error if the view direction is
+    /// undefined or parallel to `up`, or if the projection is degenerate.
+    pub fn look_at(
+        eye: Point3,
+        target: Point3,
+        up: Point3,
+        projection: Projection,
+    ) -> Result<Camera, Error> {
+        let valid_projection = match projection {
+            Projection::Perspective { fov_y } => 0. < fov_y && fov_y < std::f64::consts::PI,
+            Projection::Orthographic { height } => 0. < height && height.is_finite(),
+        };
+        if !valid_projection || !eye.is_finite() {
+            return Err(Error::InvalidCamera);
+        }
+
+        let forward = (target - eye).normalize().ok_or(Error::InvalidCamera)?;
+        let right = forward.cross(up).normalize().ok_or(Error::InvalidCamera)?;
+        Ok(Camera {
+            eye,
+            right,
+            up: right.cross(forward),
+            forward,
+            projection,
+            near: 0.1,
+        })
+    }
+
+    /// Returns the camera with its near plane at the given distance in front of
+    /// it. Anything closer is clipped away. The default is 0.1. Returns an error
+    /// if the distance is not positive and finite, as points at or behind the
+    /// camera can't be projected.
+    pub fn with_near(mut self, near: f64) -> Result<Camera, Error> {
+        if !(0. < near && near.is_finite()) {
+            return Err(Error::InvalidCamera);
+        }
+        self.near = near;
+        Ok(self)
+    }
+
+    pub fn projection(&self) -> Projection {
+        self.projection
+    }
+
+    /// Returns the position of `point` relative to the camera: its distance to
+    /// the right of, above and in front of the camera.
+    pub fn to_view(&self, point: Point3) -> Point3 {
+        let d = point - self.eye;
+        Point3::new(d.dot(self.right), d.dot(self.up), d.dot(self.forward))
+    }
+
+    /// Returns the image of `point` and its depth, or `None` if it is closer
+    /// than the near plane.
+    pub fn project(&self, point: Point3) -> Option<(Point, f64)> {
+        let view = self.to_view(point);
+        if view.z < self.near {
+            None
+        } else {
+            Some(self.project_view(view))
+        }
+    }
+
+    /// Returns the image and depth of a point in view coordinates, which must
+    /// not be closer than the near plane.
+    pub(crate) fn project_view(&self, view: Point3) -> (Point, f64) {
+        match self.projection {
+            Projection::Perspective { fov_y } => {
+                let scale = 1. / (view.z * (fov_y / 2.).tan());
+                (Point::new(view.x * scale, view.y * scale), 1. / view.z)
+            }
+            Projection::Orthographic { height } => {
+                let scale = 2. / height;
+                (Point::new(view.x * scale, view.y * scale), -view.z)
+            }
+        }
+    }
+
+    /// Clip a ring of points in view coordinates to the part at or beyond the
+    /// near plane.
+    pub(crate) fn clip_near(&self, ring: &[Point3]) -> Vec<Point3> {
+        let mut clipped = Vec::with_capacity(ring.len() + 1);
+        let mut last = match ring.last() {
+            Some(&last) => last,
+            None => return clipped,
+        };
+        for &point in ring {
+            let last_in = last.z >= self.near;
+            let point_in = point.z >= self.near;
+            if last_in != point_in {
+                let t = (self.near - last.z) / (point.z - last.z);
+                let mut crossing = last.lerp(point, t);
+                crossing.z = self.near;
+                clipped.push(crossing);
+            }
+            if point_in {
+                clipped.push(point);
+            }
+            last = point;
+        }
+        clipped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::FRAC_PI_2;
+
+    fn origin_camera(projection: Projection) -> Camera {
+        Camera::look_at(
+            Point3::new(0., 0., 0.),
+            Point3::new(0., 0., -1.),
+            Point3::new(0., 1., 0.),
+            projection,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_look_at() {
+        let camera = origin_camera(Projection::Orthographic { height: 2. });
+        assert_eq!(
+            Point3::new(1., 2., 3.),
+            camera.to_view(Point3::new(1., 2., -3.))
+        );
+
+        let up = Point3::new(0., 1., 0.);
+        let ortho = Projection::Orthographic { height: 2. };
+        let eye = Point3::new(0., 0., 0.);
+        assert!(Camera::look_at(eye, eye, up, ortho).is_err());
+        assert!(Camera::look_at(eye, Point3::new(0., 5., 0.), up, ortho).is_err());
+        assert!(Camera::look_at(
+            eye,
+            Point3::new(0., 0., 1.),
+            up,
+            Projection::Perspective { fov_y: 0. }
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_perspective() {
+        let camera = origin_camera(Projection::Perspective { fov_y: FRAC_PI_2 });
+
+        let (point, depth) = camera.project(Point3::new(1., -1., -2.)).unwrap();
+        assert!(point.approx_eq(Point::new(0.5, -0.5), 1e-12));
+        assert_eq!(0.5, depth);
+
+        // Behind the camera, or in front of the near plane.
+        assert_eq!(None, camera.project(Point3::new(0., 0., 1.)));
+        assert_eq!(None, camera.project(Point3::new(0., 0., -0.05)));
+        assert!(camera
+            .with_near(0.01)
+            .unwrap()
+            .project(Point3::new(0., 0., -0.05))
+            .is_some());
+    }
+
+    #[test]
+    fn test_orthographic() {
+        let camera = origin_camera(Projection::Orthographic { height: 4. });
+
+        let (point, depth) = camera.project(Point3::new(1., -1., -2.)).unwrap();
+        assert_eq!(Point::new(0.5, -0.5), point);
+        assert_eq!(-2., depth);
+    }
+
+    #[test]
+    fn test_clip_near() {
+        let camera = origin_camera(Projection::Perspective { fov_y: FRAC_PI_2 })
+            .with_near(1.)
+            .unwrap();
+        let ring = vec![
+            Point3::new(0., 0., 0.),
+            Point3::new(2., 0., 2.),
+            Point3::new(0., 2., 2.),
+        ];
+
+        assert_eq!(
+            vec![
+                Point3::new(0., 1., 1.),
+                Point3::new(1., 0., 1.),
+                Point3::new(2., 0., 2.),
+                Point3::new(0., 2., 2.),
+            ],
+            camera.clip_near(&ring)
+        );
+        assert!(camera.with_near(3.).unwrap().clip_near(&ring).is_empty());
+    }
+
+    #[test]
+    fn test_invalid_near() {
+        let camera = origin_camera(Projection::Perspective { fov_y: FRAC_PI_2 });
+        for near in &[0., -1., f64::NAN, f64::INFINITY] {
+            assert!(
+                matches!(camera.with_near(*near), Err(Error::InvalidCamera)),
+                "{}",
+                near
+            );
+        }
+    }
+}
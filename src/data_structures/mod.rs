@@ -1,4 +1,3 @@
-#[allow(unused)]
 mod red_black_tree;
 
 pub use red_black_tree::RedBlackTree;
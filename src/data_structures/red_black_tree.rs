@@ -1,7 +1,8 @@
+#![forbid(unsafe_code)]
+
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fmt::Debug;
-use std::pin::Pin;
-use std::ptr::NonNull;
 
 /// Node color, red or black.
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -27,325 +28,89 @@ impl ChildType {
     }
 }
 
-/// A non-null pointer to a RedBlackTreeNode.
-type NodePointer<'pointer, T> = NonNull<RedBlackTreeNode<'pointer, T>>;
+/// Index of a node in the tree's arena of nodes.
+type NodeId = usize;
 
-/// Container that optionally owns a RedBlackTreeNode in a static location on the heap.
-struct NodeContainer<'node, T: Debug> {
-    value: Option<Pin<Box<RedBlackTreeNode<'node, T>>>>,
-}
+/// A hash map from the address of a key to the node holding it, used to directly find the node
+/// corresponding to the key. Addresses are only compared, never dereferenced.
+type NodeIndex<T> = HashMap<*const T, NodeId>;
 
-impl<'node, T: Debug> Debug for NodeContainer<'node, T> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match &self.get() {
-            Some(v) => v.fmt(f),
-            None => write!(f, "()"),
-        }
-    }
-}
-
-impl<'node, T: Debug> NodeContainer<'node, T> {
-    /// Empties this container and returns its (optional) value.
-    fn take(&mut self) -> Option<Pin<Box<RedBlackTreeNode<'node, T>>>> {
-        self.value.take()
-    }
-
-    /// Returns an optional mutable reference to the RedBlackTree in this container.
-    fn get_mut(&mut self) -> Option<&mut RedBlackTreeNode<'node, T>> {
-        match &mut self.value {
-            Some(v) => {
-                let c = Pin::into_inner(v.as_mut());
-                Some(c)
-            }
-            None => None,
-        }
-    }
-
-    /// Returns an optional reference to the RedBlackTree in this container.
-    pub fn get(&self) -> Option<&RedBlackTreeNode<'node, T>> {
-        match &self.value {
-            Some(v) => {
-                let c = Pin::into_inner(v.as_ref());
-                Some(c)
-            }
-            None => None,
-        }
-    }
-
-    /// Create a new empty container.
-    fn new() -> Self {
-        NodeContainer { value: None }
-    }
-
-    /// Returns an optional NonNull pointer to the content of this container.
-    fn get_ptr(&self) -> Option<NodePointer<'node, T>> {
-        match &self.value {
-            Some(p) => NonNull::new((&**p) as *const RedBlackTreeNode<T> as *mut _),
-            None => None,
-        }
-    }
-
-    /// Returns true if this container is empty.
-    fn empty(&self) -> bool {
-        self.value.is_none()
-    }
-}
-
-/// A mutably borrowed reference to a NodeContainer.
-type NodeContainerRef<'pointer, 'node, T> = &'pointer mut NodeContainer<'node, T>;
-
-/// A hash map from key to NodePointer, used to directly find the RedBlackTreeNode corresponding to the key.
-type NodeCache<'keys, T> = HashMap<*const T, NodePointer<'keys, T>>;
-
-/// A descriptor for a location of a node in the tree, either by reference to a parent or as the root.
-#[derive(PartialEq, Debug)]
-enum TreePosition<'position, T: Debug> {
-    /// Position of the root node with reference to its NodeContainer.
-    Root(NonNull<NodeContainer<'position, T>>),
+/// A descriptor for a location of a node in the tree, either as the root or by reference to a parent.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum TreePosition {
+    /// Position of the root node.
+    Root,
     /// Position of a non-root node by reference to its parent.
-    Child(NodePointer<'position, T>, ChildType),
-}
-
-impl<'position, T: Debug> Clone for TreePosition<'position, T> {
-    fn clone(&self) -> Self {
-        match self {
-            TreePosition::Child(ptr, ct) => TreePosition::Child(*ptr, *ct),
-            TreePosition::Root(r) => TreePosition::Root(*r),
-        }
-    }
+    Child(NodeId, ChildType),
 }
 
-impl<'position, T: Debug> TreePosition<'position, T> {
-    fn is_root(&self) -> bool {
-        matches!(&self, TreePosition::Root(_))
-    }
-
-    /// Returns the parent to this node, if it exists.
-    /// This is unsafe because the resulting borrow aliases the pointer passed in, which is not consumed.
-    unsafe fn parent(&self) -> Option<&mut RedBlackTreeNode<'position, T>> {
+impl TreePosition {
+    /// Returns the parent of the node in this position, if it is not the root.
+    fn parent(self) -> Option<NodeId> {
         match self {
-            TreePosition::Child(ptr, _) => Some(&mut *ptr.as_ptr()),
-            TreePosition::Root(_) => None,
+            TreePosition::Child(parent, _) => Some(parent),
+            TreePosition::Root => None,
         }
     }
 
     /// Returns the ChildType of this node. Panics if this is a root node.
-    fn child_type(&self) -> ChildType {
+    fn child_type(self) -> ChildType {
         match self {
-            TreePosition::Child(_, ct) => *ct,
+            TreePosition::Child(_, ct) => ct,
             _ => panic!("Root does not have a child type."),
         }
     }
 
     /// Returns the position of this node's sibling, which may be a leaf node. Panics if this is a root node.
-    fn sibling(&self) -> TreePosition<'position, T> {
+    fn sibling(self) -> TreePosition {
         match self {
-            TreePosition::Child(ptr, ct) => TreePosition::Child(*ptr, ct.sibling_type()),
+            TreePosition::Child(parent, ct) => TreePosition::Child(parent, ct.sibling_type()),
             _ => panic!("Root does not have a sibling."),
         }
     }
-
-    /// Returns the node in this position. Unsafe because the TreePosition does not own its node, but useful for
-    /// getting nodes based on position.
-    unsafe fn get(&self) -> Option<&mut RedBlackTreeNode<'position, T>> {
-        match self {
-            TreePosition::Child(ptr, ct) => (*ptr.as_ptr()).child_mut(*ct),
-            TreePosition::Root(r) => (*r.as_ptr()).get_mut(),
-        }
-    }
-
-    /// Returns a pointer to the container for the node referred to.
-    unsafe fn get_container(&self) -> NodeContainerRef<'position, 'position, T> {
-        match self {
-            TreePosition::Child(ptr, ct) => (*ptr.as_ptr()).child_container_mut(*ct),
-            TreePosition::Root(r) => &mut *r.as_ptr(),
-        }
-    }
-
-    /// Sets the value of this container to the given RedBlackTree.
-    fn set(&self, value: RedBlackTreeNode<'position, T>) {
-        self.set_pinned(Some(Box::pin(value)));
-    }
-
-    /// Sets the value of this container to the given already-pinned RedBlackTree.
-    fn set_pinned(&self, value: Option<Pin<Box<RedBlackTreeNode<'position, T>>>>) {
-        let container = unsafe { self.get_container() };
-        match value {
-            Some(mut v) => {
-                v.position = self.clone();
-                container.value.replace(v);
-            }
-            None => {
-                container.value.take();
-            }
-        };
-    }
 }
 
-/// A node of the tree. Nodes own a reference to their key and own their (optional) children.
+/// A node of the tree. Nodes hold a reference to their key, and refer to their parent and
+/// (optional) children by index.
 struct RedBlackTreeNode<'node, T: Debug> {
     key: &'node T,
     color: Color,
-    position: TreePosition<'node, T>,
-    left_child: NodeContainer<'node, T>,
-    right_child: NodeContainer<'node, T>,
+    position: TreePosition,
+    left_child: Option<NodeId>,
+    right_child: Option<NodeId>,
 }
 
 impl<'node, T: Debug> RedBlackTreeNode<'node, T> {
-    /// Returns an optional reference to the child of the type provided.
-    #[allow(unused)]
-    fn child(&self, child_type: ChildType) -> Option<&RedBlackTreeNode<'node, T>> {
-        self.child_container(child_type).get()
-    }
-
-    /// Returns a mutable reference to the container of the requested child node.
-    #[allow(unused)]
-    fn child_container<'a>(&'a self, child_type: ChildType) -> &'a NodeContainer<'node, T> {
+    /// Returns the child of the type provided, if any.
+    fn child(&self, child_type: ChildType) -> Option<NodeId> {
         match child_type {
-            ChildType::Left => &self.left_child,
-            ChildType::Right => &self.right_child,
+            ChildType::Left => self.left_child,
+            ChildType::Right => self.right_child,
         }
     }
 
-    /// Returns an optional mutable reference to the child of the type provided.
-    fn child_mut(&mut self, child_type: ChildType) -> Option<&mut RedBlackTreeNode<'node, T>> {
-        self.child_container_mut(child_type).get_mut()
-    }
-
-    /// Returns a mutable reference to the container of the requested child node.
-    fn child_container_mut<'a>(
-        &'a mut self,
-        child_type: ChildType,
-    ) -> NodeContainerRef<'a, 'node, T> {
+    /// Returns a mutable reference to the requested child slot.
+    fn child_mut(&mut self, child_type: ChildType) -> &mut Option<NodeId> {
         match child_type {
             ChildType::Left => &mut self.left_child,
             ChildType::Right => &mut self.right_child,
         }
     }
-
-    /// Sets the given child of this node to the given value. Assumes that this child
-    /// is currently empty, but this is not checked because it is only used as a helper
-    /// for `rotate`, which calls `take` in each case before `set_child`.
-    fn set_child(
-        &mut self,
-        child: Option<Pin<Box<RedBlackTreeNode<'node, T>>>>,
-        child_type: ChildType,
-    ) {
-        let position = TreePosition::Child(NonNull::new(self as *mut _).unwrap(), child_type);
-        position.set_pinned(child);
-    }
-
-    /// Rotate this node in the given direction. If the direction is Right, the left child
-    /// of this node becomes its parent. If the direction is Left, the right child of this
-    /// node becomes its parent.
-    fn rotate(&mut self, direction: ChildType) {
-        let position = self.position.clone();
-        let container = unsafe { self.position.get_container() };
-        let mut new_root = self
-            .child_container_mut(direction.sibling_type())
-            .take()
-            .unwrap();
-        let pivot_child = new_root.child_container_mut(direction).take();
-
-        self.set_child(pivot_child, direction.sibling_type());
-        new_root.set_child(container.take(), direction);
-        position.set_pinned(Some(new_root));
-    }
-
-    /// Returns the color of an optional node. The `None` value here represents a leaf node,
-    /// which is black by definition in a red-black tree.
-    fn node_color(node: &Option<&mut RedBlackTreeNode<T>>) -> Color {
-        match node {
-            Some(v) => v.color,
-            None => Color::Black,
-        }
-    }
-
-    /// Repair the tree after a given newly inserted node.
-    fn repair_tree_after_insert(&mut self) {
-        let parent_container = unsafe { self.position.parent() };
-
-        if let Some(RedBlackTreeNode {
-            color: Color::Black,
-            ..
-        }) = parent_container
-        {
-            // Insert case 2: parent is black, do nothing.
-        } else if let Some(mut parent) = parent_container {
-            let grandparent = unsafe { parent.position.parent() }.expect(
-                "Parent node is red, so it should not be the root, but it does not have a parent.",
-            );
-            let sibling = parent.position.sibling();
-            let uncle = unsafe { sibling.get() };
-            let uncle_color = RedBlackTreeNode::node_color(&uncle);
-
-            if uncle_color == Color::Red {
-                // Insert case 3: parent and uncle are red; color both black and grandparent red.
-                parent.color = Color::Black;
-                uncle.unwrap().color = Color::Black;
-                grandparent.color = Color::Red;
-                grandparent.repair_tree_after_insert();
-            } else {
-                // Insert case 4.
-                let rotate_direction =
-                    match (self.position.child_type(), parent.position.child_type()) {
-                        (ChildType::Left, ChildType::Left) => ChildType::Right,
-                        (ChildType::Right, ChildType::Right) => ChildType::Left,
-                        (ChildType::Right, ChildType::Left) => {
-                            parent.rotate(ChildType::Left);
-                            parent = self;
-                            ChildType::Right
-                        }
-                        (ChildType::Left, ChildType::Right) => {
-                            parent.rotate(ChildType::Right);
-                            parent = self;
-                            ChildType::Left
-                        }
-                    };
-
-                let grandparent = unsafe { parent.position.parent() }.expect(
-                    "Parent node is red, so it should not be the root, but it does not have a parent.",
-                );
-                grandparent.rotate(rotate_direction);
-                parent.color = Color::Black;
-                grandparent.color = Color::Red;
-            }
-        } else {
-            // Insert case 1: node is root; color black.
-            self.color = Color::Black;
-        }
-    }
-}
-
-impl<'a, T: Debug> Debug for RedBlackTreeNode<'a, T> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "({:?} {:?} {:?} {:?})",
-            self.key, self.color, self.left_child, self.right_child
-        )
-    }
 }
 
 /// Cursor that points to an existing node in the tree. Contains a
-/// mutable reference to a tree's `NodeCache`, because it needs to be
-/// updated for inserts and deletes. This means that only one NodeCursor
-/// may exist at once, even though the `node` references might not conflict.
+/// mutable reference to the tree, because it needs to be updated for
+/// inserts and deletes. This means that only one NodeCursor may exist
+/// at once.
 pub struct NodeCursor<'cursor, 'tree, T: Debug> {
-    node: &'cursor mut RedBlackTreeNode<'tree, T>,
-    node_cache: &'cursor mut NodeCache<'tree, T>,
+    tree: &'cursor mut RedBlackTree<'tree, T>,
+    node: NodeId,
 }
 
 impl<'cursor, 'tree, T: Debug> NodeCursor<'cursor, 'tree, T> {
     /// Convert into a cursor for the given child.
     fn child(self, child_type: ChildType) -> TreeCursor<'cursor, 'tree, T> {
-        let position = TreePosition::Child(NonNull::new(self.node as *mut _).unwrap(), child_type);
-        let container = self.node.child_container_mut(child_type);
-        if container.empty() {
-            TreeCursor::leaf_from_position(position, self.node_cache)
-        } else {
-            TreeCursor::from_node(container.get_mut().unwrap(), self.node_cache)
-        }
+        TreeCursor::from_position(self.tree, TreePosition::Child(self.node, child_type))
     }
 
     /// Convert into a cursor to the left child.
@@ -358,87 +123,71 @@ impl<'cursor, 'tree, T: Debug> NodeCursor<'cursor, 'tree, T> {
         self.child(ChildType::Right)
     }
 
-    /// Convert into an (optional) cursor for the parent node. Although we don't own a
-    /// reference to the parent, this is safe because we own a mutable reference to the
-    /// tree's `NodeCache`, and therefore another cursor referencing the parent can't
-    /// exist in an overlapping lifetime.
+    /// Convert into an (optional) cursor for the parent node.
+    #[allow(dead_code)]
     pub fn parent(self) -> Option<NodeCursor<'cursor, 'tree, T>> {
-        match self.node.position {
-            TreePosition::Root(_) => None,
-            TreePosition::Child(parent, _) => Some(NodeCursor {
-                node: unsafe { &mut *parent.as_ptr() },
-                node_cache: self.node_cache,
-            }),
-        }
+        let parent = self.tree.nodes[self.node].position.parent()?;
+        Some(NodeCursor {
+            tree: self.tree,
+            node: parent,
+        })
     }
 
     /// Return the key associated with the node at this cursor.
-    pub fn key(&self) -> &T {
-        self.node.key
+    pub fn key(&self) -> &'tree T {
+        self.tree.nodes[self.node].key
     }
 
-    pub fn repair_tree_after_delete(self) {
-        let container = unsafe { self.node.position.get_container() };
-        let node = container.get_mut();
-        if let Some(r) = node {
-            if self.node.position.is_root() {
-                // Delete case 1: node is root; color black.
-                r.color = Color::Black;
-            } else {
-                unimplemented!()
-            }
-        }
+    /// Convert into an (optional) cursor for the next node in order.
+    pub fn next(self) -> Option<NodeCursor<'cursor, 'tree, T>> {
+        let node = self.tree.adjacent(self.node, ChildType::Right)?;
+        Some(NodeCursor {
+            tree: self.tree,
+            node,
+        })
+    }
+
+    /// Convert into an (optional) cursor for the previous node in order.
+    pub fn prev(self) -> Option<NodeCursor<'cursor, 'tree, T>> {
+        let node = self.tree.adjacent(self.node, ChildType::Left)?;
+        Some(NodeCursor {
+            tree: self.tree,
+            node,
+        })
     }
 
     /// Delete the node from the tree.
     pub fn delete(self) {
-        self.node_cache.remove(&(self.node.key as *const _));
-
-        let replacement = if self.node.left_child.empty() {
-            self.node.right_child.take()
-        } else if self.node.right_child.empty() {
-            self.node.left_child.take()
-        } else {
-            unimplemented!()
-        };
-
-        self.node.position.set_pinned(replacement);
-
-        self.repair_tree_after_delete();
+        self.tree.delete(self.node);
     }
 }
 
 /// Cursor that points to a leaf node in a tree, allowing insertion.
 pub struct LeafCursor<'cursor, 'tree, T: Debug> {
-    position: TreePosition<'tree, T>,
-    nodes: &'cursor mut HashMap<*const T, NodePointer<'tree, T>>,
+    tree: &'cursor mut RedBlackTree<'tree, T>,
+    position: TreePosition,
 }
 
 impl<'cursor, 'tree, T: Debug> LeafCursor<'cursor, 'tree, T> {
     /// Insert the key into this node's position in the tree. Consumes this
     /// `LeafCursor` and returns a `NodeCursor` to the inserted node.
     pub fn insert(self, key: &'tree T) -> NodeCursor<'cursor, 'tree, T> {
-        let node = RedBlackTreeNode {
+        let node = self.tree.allocate(RedBlackTreeNode {
             key,
             color: Color::Red,
-            position: self.position.clone(), // Note: this is overwritten anyway.
-            left_child: NodeContainer::new(),
-            right_child: NodeContainer::new(),
-        };
-
-        let container = unsafe { self.position.get_container() };
-
-        self.position.set(node);
-        self.nodes.insert(key, container.get_ptr().unwrap());
+            position: self.position,
+            left_child: None,
+            right_child: None,
+        });
+        self.tree.set(self.position, Some(node));
+        self.tree.index.insert(key, node);
 
-        let cur = NodeCursor {
-            node: container.get_mut().unwrap(),
-            node_cache: self.nodes,
-        };
-
-        cur.node.repair_tree_after_insert();
+        self.tree.repair_tree_after_insert(node);
 
-        cur
+        NodeCursor {
+            tree: self.tree,
+            node,
+        }
     }
 }
 
@@ -451,6 +200,7 @@ pub enum TreeCursor<'cursor, 'tree, T: 'cursor + Debug> {
 
 impl<'cursor, 'tree, T: Debug> TreeCursor<'cursor, 'tree, T> {
     /// Extract a `NodeCursor` from this. Panic if it is a leaf.
+    #[allow(dead_code)]
     pub fn unwrap_node(self) -> NodeCursor<'cursor, 'tree, T> {
         if let TreeCursor::Node(n) = self {
             n
@@ -468,91 +218,454 @@ impl<'cursor, 'tree, T: Debug> TreeCursor<'cursor, 'tree, T> {
         }
     }
 
-    /// Construct a `TreeCursor::Node` from a mutable node reference.
-    fn from_node(
-        node: &'cursor mut RedBlackTreeNode<'tree, T>,
-        nodes: &'cursor mut NodeCache<'tree, T>,
-    ) -> TreeCursor<'cursor, 'tree, T> {
-        TreeCursor::Node(NodeCursor {
-            node,
-            node_cache: nodes,
-        })
-    }
-
-    /// Construct a `TreeCursor::Leaf` from a container and position.
-    fn leaf_from_position(
-        position: TreePosition<'tree, T>,
-        nodes: &'cursor mut NodeCache<'tree, T>,
+    /// Construct a cursor for whatever is at the given position, a node or a leaf.
+    fn from_position(
+        tree: &'cursor mut RedBlackTree<'tree, T>,
+        position: TreePosition,
     ) -> TreeCursor<'cursor, 'tree, T> {
-        TreeCursor::Leaf(LeafCursor { position, nodes })
+        match tree.get_at(position) {
+            Some(node) => TreeCursor::Node(NodeCursor { tree, node }),
+            None => TreeCursor::Leaf(LeafCursor { tree, position }),
+        }
     }
 }
 
 /// A data structure which is both ordered and indexed by key, allowing a list
 /// to be maintained with random inserts, swaps, and deletions.
-/// The implementation combines a red-black tree with a hashmap.
+/// The implementation combines a red-black tree with a hashmap. Nodes are stored
+/// in an arena and refer to each other by index.
 pub struct RedBlackTree<'tree, T: Debug> {
-    nodes: NodeCache<'tree, T>,
-    root: NodeContainer<'tree, T>,
+    nodes: Vec<RedBlackTreeNode<'tree, T>>,
+    /// Indices of nodes which have been deleted, available for reuse.
+    free: Vec<NodeId>,
+    index: NodeIndex<T>,
+    root: Option<NodeId>,
 }
 
 impl<'tree, T: Debug> RedBlackTree<'tree, T> {
     /// Construct an empty tree.
     pub fn new() -> RedBlackTree<'tree, T> {
         RedBlackTree {
-            nodes: HashMap::new(),
-            root: NodeContainer::new(),
+            nodes: Vec::new(),
+            free: Vec::new(),
+            index: HashMap::new(),
+            root: None,
         }
     }
 
     /// Returns a `NodeCursor` for the given node, if it is found in the tree.
     pub fn get<'cursor>(&'cursor mut self, key: *const T) -> Option<NodeCursor<'cursor, 'tree, T>> {
-        let node = unsafe { &mut *self.nodes.get(&key)?.as_ptr() };
-        Some(NodeCursor {
-            node,
-            node_cache: &mut self.nodes,
-        })
+        let node = *self.index.get(&key)?;
+        Some(NodeCursor { tree: self, node })
     }
 
     /// Returns the root node of the tree, whether it is a node or a leaf.
     pub fn root<'cursor>(&'cursor mut self) -> TreeCursor<'cursor, 'tree, T> {
-        if self.root.empty() {
-            let mm = NonNull::from(&mut self.root);
-            TreeCursor::leaf_from_position(TreePosition::Root(mm), &mut self.nodes)
-        } else {
-            let v = self.root.get_mut().unwrap();
-            TreeCursor::from_node(v, &mut self.nodes)
+        TreeCursor::from_position(self, TreePosition::Root)
+    }
+
+    /// Insert the key in order according to the comparator, which is called with the new
+    /// key and the key of an existing node. Keys which compare equal to existing keys are
+    /// inserted after them. Returns a `NodeCursor` to the inserted node.
+    pub fn insert_by<'cursor, F>(
+        &'cursor mut self,
+        key: &'tree T,
+        mut cmp: F,
+    ) -> NodeCursor<'cursor, 'tree, T>
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let mut cursor = self.root();
+        while let TreeCursor::Node(node) = cursor {
+            cursor = if cmp(key, node.key()) == Ordering::Less {
+                node.left_child()
+            } else {
+                node.right_child()
+            };
+        }
+        cursor.unwrap_leaf().insert(key)
+    }
+
+    /// Search the tree for a node using a function which returns the ordering of a node's
+    /// key relative to the target, as in `slice::binary_search_by`. Returns a
+    /// `NodeCursor` to a node for which the function returns `Equal`, if there is one.
+    #[allow(dead_code)]
+    pub fn find_by<'cursor, F>(&'cursor mut self, mut f: F) -> Option<NodeCursor<'cursor, 'tree, T>>
+    where
+        F: FnMut(&T) -> Ordering,
+    {
+        let mut cursor = self.root();
+        while let TreeCursor::Node(node) = cursor {
+            cursor = match f(node.key()) {
+                Ordering::Less => node.right_child(),
+                Ordering::Greater => node.left_child(),
+                Ordering::Equal => return Some(node),
+            };
         }
+        None
+    }
+
+    /// Returns true if the tree contains no keys.
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Returns a cursor for the node with the smallest key, if the tree is not empty.
+    #[allow(dead_code)]
+    pub fn first<'cursor>(&'cursor mut self) -> Option<NodeCursor<'cursor, 'tree, T>> {
+        let node = self.outermost(self.root?, ChildType::Left);
+        Some(NodeCursor { tree: self, node })
+    }
+
+    /// Returns a cursor for the node with the largest key, if the tree is not empty.
+    #[allow(dead_code)]
+    pub fn last<'cursor>(&'cursor mut self) -> Option<NodeCursor<'cursor, 'tree, T>> {
+        let node = self.outermost(self.root?, ChildType::Right);
+        Some(NodeCursor { tree: self, node })
+    }
+
+    /// Returns an iterator over the keys of the tree, in order.
+    pub fn iter(&self) -> Iter<'_, 'tree, T> {
+        let mut iter = Iter {
+            tree: self,
+            stack: Vec::new(),
+        };
+        iter.push_left_edge(self.root);
+        iter
     }
 
     /// Swap the positions of the nodes associated with each key in the tree. Simply swaps the keys
     /// within the nodes; the tree structure exactly the same.
     pub fn swap(&mut self, key1: *const T, key2: *const T) {
-        let node1 = unsafe { &mut *self.nodes.remove(&key1).unwrap().as_ptr() };
-        let node2 = unsafe { &mut *self.nodes.remove(&key2).unwrap().as_ptr() };
+        let node1 = self.index[&key1];
+        let node2 = self.index[&key2];
+
+        let key = self.nodes[node1].key;
+        self.nodes[node1].key = self.nodes[node2].key;
+        self.nodes[node2].key = key;
 
-        std::mem::swap(&mut node1.key, &mut node2.key);
+        self.index.insert(key1, node2);
+        self.index.insert(key2, node1);
+    }
 
-        self.nodes.insert(
-            node1.key,
-            NonNull::new(node1 as *const RedBlackTreeNode<T> as *mut _).unwrap(),
+    /// Store a node in the arena, reusing the slot of a deleted node if there is one.
+    fn allocate(&mut self, node: RedBlackTreeNode<'tree, T>) -> NodeId {
+        match self.free.pop() {
+            Some(id) => {
+                self.nodes[id] = node;
+                id
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    /// Returns the node in the given position, if it is not a leaf.
+    fn get_at(&self, position: TreePosition) -> Option<NodeId> {
+        match position {
+            TreePosition::Root => self.root,
+            TreePosition::Child(parent, ct) => self.nodes[parent].child(ct),
+        }
+    }
+
+    /// Puts the given node (or a leaf) in the given position, replacing whatever was there.
+    fn set(&mut self, position: TreePosition, node: Option<NodeId>) {
+        match position {
+            TreePosition::Root => self.root = node,
+            TreePosition::Child(parent, ct) => *self.nodes[parent].child_mut(ct) = node,
+        }
+        if let Some(node) = node {
+            self.nodes[node].position = position;
+        }
+    }
+
+    /// Returns the color of an optional node. The `None` value here represents a leaf node,
+    /// which is black by definition in a red-black tree.
+    fn node_color(&self, node: Option<NodeId>) -> Color {
+        match node {
+            Some(v) => self.nodes[v].color,
+            None => Color::Black,
+        }
+    }
+
+    /// Rotate the given node in the given direction. If the direction is Right, the left child
+    /// of this node becomes its parent. If the direction is Left, the right child of this
+    /// node becomes its parent.
+    fn rotate(&mut self, node: NodeId, direction: ChildType) {
+        let position = self.nodes[node].position;
+        let new_root = self.nodes[node]
+            .child(direction.sibling_type())
+            .expect("Cannot rotate without a child to take the node's place.");
+        let pivot_child = self.nodes[new_root].child(direction);
+
+        self.set(
+            TreePosition::Child(node, direction.sibling_type()),
+            pivot_child,
         );
-        self.nodes.insert(
-            node2.key,
-            NonNull::new(node2 as *const RedBlackTreeNode<T> as *mut _).unwrap(),
+        self.set(TreePosition::Child(new_root, direction), Some(node));
+        self.set(position, Some(new_root));
+    }
+
+    /// Repair the tree after a given newly inserted node.
+    fn repair_tree_after_insert(&mut self, node: NodeId) {
+        let mut node = node;
+        let mut parent = match self.nodes[node].position.parent() {
+            Some(parent) => parent,
+            None => {
+                // Insert case 1: node is root; color black.
+                self.nodes[node].color = Color::Black;
+                return;
+            }
+        };
+
+        if self.nodes[parent].color == Color::Black {
+            // Insert case 2: parent is black, do nothing.
+            return;
+        }
+
+        let grandparent = self.nodes[parent].position.parent().expect(
+            "Parent node is red, so it should not be the root, but it does not have a parent.",
         );
+        let uncle = self.get_at(self.nodes[parent].position.sibling());
+
+        if let (Color::Red, Some(uncle)) = (self.node_color(uncle), uncle) {
+            // Insert case 3: parent and uncle are red; color both black and grandparent red.
+            self.nodes[parent].color = Color::Black;
+            self.nodes[uncle].color = Color::Black;
+            self.nodes[grandparent].color = Color::Red;
+            self.repair_tree_after_insert(grandparent);
+        } else {
+            // Insert case 4.
+            let rotate_direction = match (
+                self.nodes[node].position.child_type(),
+                self.nodes[parent].position.child_type(),
+            ) {
+                (ChildType::Left, ChildType::Left) => ChildType::Right,
+                (ChildType::Right, ChildType::Right) => ChildType::Left,
+                (ChildType::Right, ChildType::Left) => {
+                    self.rotate(parent, ChildType::Left);
+                    std::mem::swap(&mut node, &mut parent);
+                    ChildType::Right
+                }
+                (ChildType::Left, ChildType::Right) => {
+                    self.rotate(parent, ChildType::Right);
+                    std::mem::swap(&mut node, &mut parent);
+                    ChildType::Left
+                }
+            };
+
+            self.rotate(grandparent, rotate_direction);
+            self.nodes[parent].color = Color::Black;
+            self.nodes[grandparent].color = Color::Red;
+        }
+    }
+
+    /// Repair the tree after a black node has been removed from the given position, leaving
+    /// the (possibly leaf) node now in that position one black node short on every path.
+    fn repair_tree_after_delete(&mut self, position: TreePosition) {
+        let parent = match position.parent() {
+            Some(parent) => parent,
+            // Delete case 1: node is root; the black height of every path was reduced equally.
+            None => return,
+        };
+        let child_type = position.child_type();
+        let sibling_position = position.sibling();
+        let mut sibling = self
+            .get_at(sibling_position)
+            .expect("Node is one black node short, so its sibling should not be a leaf.");
+
+        if self.nodes[sibling].color == Color::Red {
+            // Delete case 2: sibling is red; rotate it above the parent so that the node
+            // has a black sibling.
+            self.nodes[parent].color = Color::Red;
+            self.nodes[sibling].color = Color::Black;
+            self.rotate(parent, child_type);
+            sibling = self.get_at(sibling_position).unwrap();
+        }
+
+        let near = self.nodes[sibling].child(child_type);
+        let far = self.nodes[sibling].child(child_type.sibling_type());
+
+        if self.node_color(near) == Color::Black && self.node_color(far) == Color::Black {
+            self.nodes[sibling].color = Color::Red;
+            if self.nodes[parent].color == Color::Black {
+                // Delete case 3: parent, sibling, and sibling's children are black; color
+                // sibling red and repair the parent, which is now one black node short.
+                self.repair_tree_after_delete(self.nodes[parent].position);
+            } else {
+                // Delete case 4: parent is red; swapping its color with the sibling's
+                // restores the black node on paths through this node.
+                self.nodes[parent].color = Color::Black;
+            }
+            return;
+        }
+
+        let far = match (self.node_color(far), near) {
+            (Color::Black, Some(near)) => {
+                // Delete case 5: the sibling's child nearest this node is red; rotate it above
+                // the sibling so that the sibling's far child is red.
+                self.nodes[sibling].color = Color::Red;
+                self.nodes[near].color = Color::Black;
+                self.rotate(sibling, child_type.sibling_type());
+                let far = sibling;
+                sibling = near;
+                far
+            }
+            _ => far.unwrap(),
+        };
+
+        // Delete case 6: the sibling's far child is red; rotate the sibling above the parent,
+        // which adds a black node to paths through this node.
+        self.nodes[sibling].color = self.nodes[parent].color;
+        self.nodes[parent].color = Color::Black;
+        self.nodes[far].color = Color::Black;
+        self.rotate(parent, child_type);
+    }
+
+    /// Delete the given node from the tree.
+    fn delete(&mut self, node: NodeId) {
+        self.index.remove(&(self.nodes[node].key as *const _));
+
+        let removed = match (self.nodes[node].left_child, self.nodes[node].right_child) {
+            (Some(_), Some(right)) => {
+                // The node has two children. Move its in-order successor's key into it,
+                // and remove the successor's node (which has no left child) instead.
+                let successor = self.outermost(right, ChildType::Left);
+                let key = self.nodes[successor].key;
+                self.nodes[node].key = key;
+                self.index.insert(key, node);
+                successor
+            }
+            _ => node,
+        };
+
+        self.unlink(removed);
+    }
+
+    /// Remove a node with at most one child from the tree, putting the child (if
+    /// any) in its place. The index is not updated.
+    fn unlink(&mut self, node: NodeId) {
+        let RedBlackTreeNode {
+            color,
+            position,
+            left_child,
+            right_child,
+            ..
+        } = self.nodes[node];
+        let replacement = left_child.or(right_child);
+
+        self.set(position, replacement);
+        self.free.push(node);
+
+        if color == Color::Red {
+            // Removing a red node does not change the black height of any path.
+            return;
+        }
+
+        match replacement {
+            // A black node with one child must have a red child, which takes its color.
+            Some(child) => self.nodes[child].color = Color::Black,
+            None => self.repair_tree_after_delete(position),
+        }
+    }
+
+    /// Returns the outermost node on the given side of the subtree rooted at the given node.
+    fn outermost(&self, node: NodeId, side: ChildType) -> NodeId {
+        let mut node = node;
+        while let Some(child) = self.nodes[node].child(side) {
+            node = child;
+        }
+        node
+    }
+
+    /// Returns the node which follows the given node in order (for `Right`) or precedes it
+    /// (for `Left`).
+    fn adjacent(&self, node: NodeId, direction: ChildType) -> Option<NodeId> {
+        // If there is a subtree in the given direction, the adjacent node is the
+        // outermost node of that subtree on the opposite side.
+        if let Some(child) = self.nodes[node].child(direction) {
+            return Some(self.outermost(child, direction.sibling_type()));
+        }
+
+        // Otherwise, it is the first ancestor whose subtree on the opposite
+        // side contains this node.
+        let mut position = self.nodes[node].position;
+        loop {
+            match position {
+                TreePosition::Root => return None,
+                TreePosition::Child(parent, child_type) => {
+                    if child_type != direction {
+                        return Some(parent);
+                    }
+                    position = self.nodes[parent].position;
+                }
+            }
+        }
+    }
+
+    /// Formats the subtree rooted at the given node.
+    fn fmt_node(&self, node: Option<NodeId>, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match node {
+            Some(node) => {
+                let node = &self.nodes[node];
+                write!(f, "({:?} {:?} ", node.key, node.color)?;
+                self.fmt_node(node.left_child, f)?;
+                write!(f, " ")?;
+                self.fmt_node(node.right_child, f)?;
+                write!(f, ")")
+            }
+            None => write!(f, "()"),
+        }
+    }
+}
+
+/// An in-order iterator over the keys of a `RedBlackTree`.
+pub struct Iter<'a, 'tree, T: Debug> {
+    tree: &'a RedBlackTree<'tree, T>,
+    /// Nodes whose key has not been visited yet, with the next node on top. The right
+    /// subtree of each of these nodes has not been visited either.
+    stack: Vec<NodeId>,
+}
+
+impl<'a, 'tree, T: Debug> Iter<'a, 'tree, T> {
+    /// Push the given node and the chain of left children below it.
+    fn push_left_edge(&mut self, mut node: Option<NodeId>) {
+        while let Some(id) = node {
+            self.stack.push(id);
+            node = self.tree.nodes[id].left_child;
+        }
+    }
+}
+
+impl<'a, 'tree, T: Debug> Iterator for Iter<'a, 'tree, T> {
+    type Item = &'tree T;
+
+    fn next(&mut self) -> Option<&'tree T> {
+        let node = &self.tree.nodes[self.stack.pop()?];
+        self.push_left_edge(node.right_child);
+        Some(node.key)
     }
 }
 
 impl<'tree, T: Debug> Debug for RedBlackTree<'tree, T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.root.fmt(f)
+        self.fmt_node(self.root, f)
     }
 }
 
+// These tests also run under Miri, which checks them for undefined behaviour:
+//
+//     cargo +nightly miri test data_structures
+//
+// Miri runs them much more slowly, taking several minutes.
 #[cfg(test)]
 mod tests {
     use super::{Color::Black, Color::Red, *};
+    use proptest::prelude::*;
+    use std::collections::btree_map::Entry;
+    use std::collections::BTreeMap;
 
     /// A representation of the "expected" shape of the tree resulting from operations.
     struct NodeExpectation {
@@ -577,70 +690,58 @@ mod tests {
         }))
     }
 
-    /// Recursively compare an expected node with the corresponding actual tree node,
+    /// Recursively compare an expected node with the node in the given position of the tree,
     /// panic if there are any issues.
     fn expect_node(
-        nodes: &mut NodeCache<usize>,
-        position: TreePosition<usize>,
-        actual: &NodeContainer<usize>,
+        tree: &RedBlackTree<usize>,
+        index: &mut NodeIndex<usize>,
+        position: TreePosition,
         expected: &Option<Box<NodeExpectation>>,
     ) {
+        let actual = tree.get_at(position);
         if let Some(expected_node) = expected {
-            let actual_ptr = actual.get().expect(&format!(
-                "Expected {:?} node with key: {:?}",
-                expected_node.color, expected_node.key
-            ));
-            let actual_node = actual_ptr;
+            let id = actual.unwrap_or_else(|| {
+                panic!(
+                    "Expected {:?} node with key: {:?}",
+                    expected_node.color, expected_node.key
+                )
+            });
+            let actual_node = &tree.nodes[id];
 
             assert_eq!(expected_node.color, actual_node.color);
             assert_eq!(expected_node.key, *actual_node.key);
             assert_eq!(actual_node.position, position);
 
-            // Ensure that the value in the tree matches this node.
-            {
-                let actual_node_ptr = nodes
-                    .remove(&(actual_node.key as *const usize))
-                    .expect("Node should be in nodes cache, but isn't.")
-                    .as_ptr() as *const _;
-                let expected_node_ptr = actual_ptr as *const RedBlackTreeNode<_>;
-                assert_eq!(actual_node_ptr, expected_node_ptr);
-            }
+            // Ensure that the value in the index matches this node.
+            let indexed = index
+                .remove(&(actual_node.key as *const usize))
+                .expect("Node should be in index, but isn't.");
+            assert_eq!(id, indexed);
 
             // Recurse left child.
             expect_node(
-                nodes,
-                TreePosition::Child(
-                    NonNull::new(actual_node as *const _ as *mut _).unwrap(),
-                    ChildType::Left,
-                ),
-                &actual_node.left_child,
+                tree,
+                index,
+                TreePosition::Child(id, ChildType::Left),
                 &expected_node.left_child,
             );
             // Recurse right child.
             expect_node(
-                nodes,
-                TreePosition::Child(
-                    NonNull::new(actual_node as *const _ as *mut _).unwrap(),
-                    ChildType::Right,
-                ),
-                &actual_node.right_child,
+                tree,
+                index,
+                TreePosition::Child(id, ChildType::Right),
                 &expected_node.right_child,
             );
         } else {
-            assert!(actual.empty());
+            assert!(actual.is_none());
         }
     }
 
     /// Compare the root of the tree with the given `NodeExpectation`.
     fn expect_tree(actual: &RedBlackTree<usize>, expected: &Option<Box<NodeExpectation>>) {
-        let mut nodes = actual.nodes.clone();
-        expect_node(
-            &mut nodes,
-            TreePosition::Root(NonNull::new(&actual.root as *const _ as *mut _).unwrap()),
-            &actual.root,
-            expected,
-        );
-        assert_eq!(0, nodes.len());
+        let mut index = actual.index.clone();
+        expect_node(actual, &mut index, TreePosition::Root, expected);
+        assert_eq!(0, index.len());
     }
 
     /// Check that the tree is valid. Returns the number of black nodes on the path to each descendent
@@ -657,25 +758,27 @@ mod tests {
     /// other properties are checked here, as well as these data structure invariants:
     ///
     /// 1. A node's position should match the position where we found it.
-    /// 2. A node's location in the node cache should point to that node.
-    /// 3. Every node in the node cache should be in the tree (i.e. we shouldn't have
-    ///    dangling pointers in the node cache).
+    /// 2. A node's entry in the index should point to that node.
+    /// 3. Every node in the index should be in the tree (i.e. we shouldn't have
+    ///    stale entries in the index).
     ///
-    /// #3 is tested by removing nodes from the node cache as they are reached; when called on the root node the resulting
-    /// node cache should be empty. The others are tested explicitly.
+    /// #3 is tested by removing nodes from the index as they are reached; when called on the root node the resulting
+    /// index should be empty. The others are tested explicitly.
     fn check_tree_node(
-        nodes: &mut NodeCache<usize>,
-        position: TreePosition<usize>,
-        actual: &NodeContainer<usize>,
+        tree: &RedBlackTree<usize>,
+        index: &mut NodeIndex<usize>,
+        position: TreePosition,
     ) -> usize {
-        if let Some(node) = actual.get() {
+        if let Some(id) = tree.get_at(position) {
+            let node = &tree.nodes[id];
+
             // Ensure that the node's reference to its position matches the position in the tree at which we
             // found it. (invariant #1)
             assert_eq!(node.position, position);
 
-            if let Some(parent) = unsafe { node.position.parent() } {
+            if let Some(parent) = position.parent() {
                 // If this node has a red parent, this node should be black. (property #4)
-                if parent.color == Color::Red {
+                if tree.nodes[parent].color == Color::Red {
                     assert_eq!(node.color, Color::Black);
                 }
             } else {
@@ -683,32 +786,13 @@ mod tests {
                 assert_eq!(Color::Black, node.color);
             }
 
-            // Ensure that the value in the tree matches this node. (invariant #2)
-            {
-                let node_ptr =
-                    nodes.remove(&(node.key as *const usize)).unwrap().as_ptr() as *const _;
-                let expected_node_ptr = node_ptr as *const RedBlackTreeNode<_>;
-                assert_eq!(node_ptr, expected_node_ptr);
-            }
+            // Ensure that the value in the index matches this node. (invariant #2)
+            assert_eq!(Some(id), index.remove(&(node.key as *const usize)));
 
             // Recurse left child.
-            let left_d = check_tree_node(
-                nodes,
-                TreePosition::Child(
-                    NonNull::new(node as *const _ as *mut _).unwrap(),
-                    ChildType::Left,
-                ),
-                &node.left_child,
-            );
+            let left_d = check_tree_node(tree, index, TreePosition::Child(id, ChildType::Left));
             // Recurse right child.
-            let right_d = check_tree_node(
-                nodes,
-                TreePosition::Child(
-                    NonNull::new(node as *const _ as *mut _).unwrap(),
-                    ChildType::Right,
-                ),
-                &node.right_child,
-            );
+            let right_d = check_tree_node(tree, index, TreePosition::Child(id, ChildType::Right));
 
             // Ensure that the black distance to ancestors along the left and right children is the same. (property #5)
             assert_eq!(left_d, right_d);
@@ -728,25 +812,22 @@ mod tests {
     /// Check a tree according to properties of a red black tree as well as invariants specific to our
     /// implementation.
     fn check_tree(actual: &RedBlackTree<usize>) {
-        let mut nodes = actual.nodes.clone();
-        check_tree_node(
-            &mut nodes,
-            TreePosition::Root(NonNull::new(&actual.root as *const _ as *mut _).unwrap()),
-            &actual.root,
-        );
-        assert_eq!(0, nodes.len());
+        let mut index = actual.index.clone();
+        check_tree_node(actual, &mut index, TreePosition::Root);
+        assert_eq!(0, index.len());
     }
 
-    fn check_bst(node: &RedBlackTreeNode<usize>) -> (usize, usize) {
+    fn check_bst(tree: &RedBlackTree<usize>, id: NodeId) -> (usize, usize) {
+        let node = &tree.nodes[id];
         let mut min = *node.key;
         let mut max = *node.key;
-        if let Some(left) = node.child(ChildType::Left) {
-            let (left_min, left_max) = check_bst(left);
+        if let Some(left) = node.left_child {
+            let (left_min, left_max) = check_bst(tree, left);
             assert!(left_max < *node.key);
             min = left_min;
         }
-        if let Some(right) = node.child(ChildType::Right) {
-            let (right_min, right_max) = check_bst(right);
+        if let Some(right) = node.right_child {
+            let (right_min, right_max) = check_bst(tree, right);
             assert!(right_min > *node.key);
             max = right_max;
         }
@@ -762,6 +843,8 @@ mod tests {
         leaf.insert(&4);
 
         expect_tree(&tree, &nd(4, Black, None, None));
+
+        check_tree(&tree);
     }
 
     #[test]
@@ -771,18 +854,20 @@ mod tests {
         let leaf = tree.root().unwrap_leaf();
         let mut root = leaf.insert(&4);
         let result = root.left_child().unwrap_leaf().insert(&3);
-        assert_eq!(&3, result.node.key);
-        assert_eq!(Red, result.node.color);
+        assert_eq!(&3, result.key());
+        assert_eq!(Red, result.tree.nodes[result.node].color);
 
         root = tree.root().unwrap_node();
         let five = root.right_child().unwrap_leaf().insert(&5);
-        assert_eq!(Red, five.node.color);
+        assert_eq!(Red, five.tree.nodes[five.node].color);
         assert_eq!(&4, five.parent().unwrap().key());
 
         expect_tree(
             &tree,
             &nd(4, Black, nd(3, Red, None, None), nd(5, Red, None, None)),
         );
+
+        check_tree(&tree);
     }
 
     #[test]
@@ -808,6 +893,8 @@ mod tests {
                 nd(6, Black, None, None),
             ),
         );
+
+        check_tree(&tree);
     }
 
     #[test]
@@ -826,6 +913,8 @@ mod tests {
                 nd(5, Color::Red, None, None),
             ),
         );
+
+        check_tree(&tree);
     }
 
     #[test]
@@ -846,6 +935,8 @@ mod tests {
                 nd(7, Color::Red, None, None),
             ),
         );
+
+        check_tree(&tree);
     }
 
     #[test]
@@ -866,6 +957,8 @@ mod tests {
                 nd(5, Color::Red, None, None),
             ),
         );
+
+        check_tree(&tree);
     }
 
     #[test]
@@ -881,6 +974,8 @@ mod tests {
             &tree,
             &nd(6, Black, nd(5, Red, None, None), nd(7, Red, None, None)),
         );
+
+        check_tree(&tree);
     }
 
     #[test]
@@ -903,10 +998,10 @@ mod tests {
             }
             let leaf = c.unwrap_leaf();
             leaf.insert(val);
+            check_tree(&t);
         }
 
-        check_tree(&t);
-        check_bst(t.root.get_mut().unwrap());
+        check_bst(&t, t.root.unwrap());
     }
 
     #[test]
@@ -924,6 +1019,8 @@ mod tests {
         println!("{:?}", tree);
 
         expect_tree(&tree, &nd(6, Black, None, nd(5, Red, None, None)));
+
+        check_tree(&tree);
     }
 
     #[test]
@@ -934,9 +1031,385 @@ mod tests {
 
         expect_tree(&tree, &nd(7, Black, nd(4, Red, None, None), None));
 
+        check_tree(&tree);
+
         c = tree.root().unwrap_node();
         c.delete();
 
         expect_tree(&tree, &nd(4, Black, None, None));
+
+        check_tree(&tree);
+    }
+
+    /// Insert each value into the tree in order.
+    fn insert_all<'tree>(tree: &mut RedBlackTree<'tree, usize>, vals: &'tree [usize]) {
+        for val in vals {
+            tree.insert_by(val, |a, b| a.cmp(b));
+            check_tree(tree);
+        }
+    }
+
+    /// Delete the node holding the key equal to `val`.
+    fn delete(tree: &mut RedBlackTree<usize>, vals: &[usize], val: usize) {
+        let key = vals.iter().find(|v| **v == val).unwrap();
+        tree.get(key).unwrap().delete();
+        check_tree(tree);
+    }
+
+    #[test]
+    fn test_iter() {
+        let vals: Vec<usize> = vec![8, 3, 10, 1, 6, 14, 4, 7, 13];
+        let mut tree = RedBlackTree::new();
+        assert_eq!(None, tree.iter().next());
+
+        insert_all(&mut tree, &vals);
+
+        let mut sorted = vals.clone();
+        sorted.sort();
+        assert_eq!(sorted, tree.iter().copied().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_first_last() {
+        let vals: Vec<usize> = vec![8, 3, 10, 1, 6, 14, 4, 7, 13];
+        let mut tree = RedBlackTree::new();
+        assert!(tree.first().is_none());
+        assert!(tree.last().is_none());
+
+        insert_all(&mut tree, &vals);
+
+        assert_eq!(&1, tree.first().unwrap().key());
+        assert_eq!(&14, tree.last().unwrap().key());
+    }
+
+    #[test]
+    fn test_next_prev() {
+        let vals: Vec<usize> = vec![8, 3, 10, 1, 6, 14, 4, 7, 13];
+        let mut tree = RedBlackTree::new();
+        insert_all(&mut tree, &vals);
+
+        let mut sorted = vals.clone();
+        sorted.sort();
+        for (i, val) in sorted.iter().enumerate() {
+            let key = vals.iter().find(|v| *v == val).unwrap();
+            let prev = tree.get(key).unwrap().prev().map(|c| c.key());
+            assert_eq!(i.checked_sub(1).map(|j| &sorted[j]), prev);
+            let next = tree.get(key).unwrap().next().map(|c| c.key());
+            assert_eq!(sorted.get(i + 1), next);
+        }
+
+        // Walk the whole tree in each direction.
+        let mut forward = Vec::new();
+        let mut c = tree.first();
+        while let Some(node) = c {
+            forward.push(*node.key());
+            c = node.next();
+        }
+        assert_eq!(sorted, forward);
+
+        let mut backward = Vec::new();
+        let mut c = tree.last();
+        while let Some(node) = c {
+            backward.push(*node.key());
+            c = node.prev();
+        }
+        backward.reverse();
+        assert_eq!(sorted, backward);
+    }
+
+    #[test]
+    fn delete_with_two_children() {
+        let vals: Vec<usize> = vec![8, 3, 10, 1, 6, 14, 4, 7, 13];
+        let mut tree = RedBlackTree::new();
+        insert_all(&mut tree, &vals);
+
+        let mut expected = vals.clone();
+        expected.sort();
+        for &val in &[3, 8, 6] {
+            delete(&mut tree, &vals, val);
+            expected.retain(|v| *v != val);
+
+            assert_eq!(expected, tree.iter().copied().collect::<Vec<_>>());
+            check_bst(&tree, tree.root.unwrap());
+            for key in tree.iter().collect::<Vec<_>>() {
+                // The node cache points to the node holding each key.
+                assert!(std::ptr::eq(key, tree.get(key).unwrap().key()));
+            }
+        }
+    }
+
+    #[test]
+    fn delete_case_three() {
+        // Parent, sibling, and the sibling's children are all black.
+        let vals: Vec<usize> = vec![10, 5, 15, 20];
+        let mut tree = RedBlackTree::new();
+        insert_all(&mut tree, &vals);
+        delete(&mut tree, &vals, 20);
+        expect_tree(
+            &tree,
+            &nd(
+                10,
+                Black,
+                nd(5, Black, None, None),
+                nd(15, Black, None, None),
+            ),
+        );
+
+        delete(&mut tree, &vals, 5);
+        expect_tree(&tree, &nd(10, Black, None, nd(15, Red, None, None)));
+    }
+
+    #[test]
+    fn delete_case_four() {
+        // Parent is red; sibling and its children are black.
+        let vals: Vec<usize> = vec![10, 5, 15, 20, 25, 30];
+        let mut tree = RedBlackTree::new();
+        insert_all(&mut tree, &vals);
+        delete(&mut tree, &vals, 30);
+        expect_tree(
+            &tree,
+            &nd(
+                10,
+                Black,
+                nd(5, Black, None, None),
+                nd(
+                    20,
+                    Red,
+                    nd(15, Black, None, None),
+                    nd(25, Black, None, None),
+                ),
+            ),
+        );
+
+        delete(&mut tree, &vals, 15);
+        expect_tree(
+            &tree,
+            &nd(
+                10,
+                Black,
+                nd(5, Black, None, None),
+                nd(20, Black, None, nd(25, Red, None, None)),
+            ),
+        );
+    }
+
+    #[test]
+    fn delete_case_two() {
+        // Sibling is red, so it is rotated above the parent before recoloring.
+        let vals: Vec<usize> = vec![10, 5, 15, 20, 25, 30];
+        let mut tree = RedBlackTree::new();
+        insert_all(&mut tree, &vals);
+        delete(&mut tree, &vals, 30);
+        delete(&mut tree, &vals, 5);
+        expect_tree(
+            &tree,
+            &nd(
+                20,
+                Black,
+                nd(10, Black, None, nd(15, Red, None, None)),
+                nd(25, Black, None, None),
+            ),
+        );
+    }
+
+    #[test]
+    fn delete_case_five() {
+        // The sibling's near child is red, so it is rotated into the sibling's place.
+        let vals: Vec<usize> = vec![10, 5, 15, 12];
+        let mut tree = RedBlackTree::new();
+        insert_all(&mut tree, &vals);
+        delete(&mut tree, &vals, 5);
+        expect_tree(
+            &tree,
+            &nd(
+                12,
+                Black,
+                nd(10, Black, None, None),
+                nd(15, Black, None, None),
+            ),
+        );
+    }
+
+    #[test]
+    fn delete_case_six() {
+        // The sibling's far child is red.
+        let vals: Vec<usize> = vec![10, 5, 15, 20];
+        let mut tree = RedBlackTree::new();
+        insert_all(&mut tree, &vals);
+        delete(&mut tree, &vals, 5);
+        expect_tree(
+            &tree,
+            &nd(
+                15,
+                Black,
+                nd(10, Black, None, None),
+                nd(20, Black, None, None),
+            ),
+        );
+    }
+
+    #[test]
+    fn delete_stress_test() {
+        let vals: Vec<usize> = vec![
+            93, 11, 3, 31, 1, 78, 16, 14, 2, 58, 19, 44, 68, 97, 41, 15, 81, 49, 79, 40, 52, 98,
+            91, 23, 95, 67, 30, 43, 62, 25, 96, 6, 100, 72, 37, 42, 38, 61, 74, 99, 39, 84, 50, 55,
+            90, 64, 75, 69, 45, 54, 26, 56, 27, 4, 18, 13, 88, 66, 51, 32,
+        ];
+        let mut tree = RedBlackTree::new();
+        insert_all(&mut tree, &vals);
+
+        let mut expected = vals.clone();
+        expected.sort();
+        // Delete in an order unrelated to the insertion order.
+        for i in 0..vals.len() {
+            let val = vals[(i * 7) % vals.len()];
+            delete(&mut tree, &vals, val);
+            expected.retain(|v| *v != val);
+
+            assert_eq!(expected, tree.iter().copied().collect::<Vec<_>>());
+        }
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn test_insert_by() {
+        // Order the keys in reverse, and keep equal keys in the order they were inserted.
+        let vals: Vec<usize> = vec![5, 3, 8, 3, 1, 8, 9, 5];
+        let mut tree = RedBlackTree::new();
+        for val in &vals {
+            let c = tree.insert_by(val, |a, b| b.cmp(a));
+            assert!(std::ptr::eq(val, c.key()));
+            check_tree(&tree);
+        }
+
+        let expected: Vec<*const usize> = vec![
+            &vals[6], &vals[2], &vals[5], &vals[0], &vals[7], &vals[1], &vals[3], &vals[4],
+        ];
+        let actual: Vec<*const usize> = tree.iter().map(|k| k as *const usize).collect();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_find_by() {
+        let vals: Vec<usize> = vec![8, 3, 10, 1, 6, 14, 4, 7, 13];
+        let mut tree = RedBlackTree::new();
+        insert_all(&mut tree, &vals);
+
+        for val in &vals {
+            let c = tree.find_by(|k| k.cmp(val)).unwrap();
+            assert!(std::ptr::eq(val, c.key()));
+        }
+        for missing in &[0, 2, 9, 15] {
+            assert!(tree.find_by(|k| k.cmp(missing)).is_none());
+        }
+    }
+
+    #[test]
+    fn delete_reuses_nodes() {
+        let vals: Vec<usize> = vec![8, 3, 10, 1, 6, 14, 4, 7, 13];
+        let mut tree = RedBlackTree::new();
+        insert_all(&mut tree, &vals);
+
+        for &val in &[3, 14, 8] {
+            delete(&mut tree, &vals, val);
+        }
+        let more: Vec<usize> = vec![2, 9, 15];
+        insert_all(&mut tree, &more);
+
+        // The slots of the deleted nodes were reused.
+        assert_eq!(vals.len(), tree.nodes.len());
+        assert_eq!(
+            vec![1, 2, 4, 6, 7, 9, 10, 13, 15],
+            tree.iter().copied().collect::<Vec<_>>()
+        );
+    }
+
+    /// Number of distinct keys used by the differential test.
+    const NUM_KEYS: usize = 32;
+
+    /// An operation applied to both a `RedBlackTree` and the reference model. Keys are
+    /// identified by their index in a pool of `NUM_KEYS` keys.
+    #[derive(Clone, Debug)]
+    enum Op {
+        Insert(usize),
+        Delete(usize),
+        Swap(usize, usize),
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        prop_oneof![
+            3 => (0..NUM_KEYS).prop_map(Op::Insert),
+            2 => (0..NUM_KEYS).prop_map(Op::Delete),
+            1 => (0..NUM_KEYS, 0..NUM_KEYS).prop_map(|(a, b)| Op::Swap(a, b)),
+        ]
+    }
+
+    /// Miri is slow and isolates the tests from the file system, so under Miri run fewer
+    /// cases and don't persist failures.
+    fn proptest_config() -> ProptestConfig {
+        if cfg!(miri) {
+            ProptestConfig {
+                cases: 8,
+                failure_persistence: None,
+                ..ProptestConfig::default()
+            }
+        } else {
+            ProptestConfig::default()
+        }
+    }
+
+    proptest! {
+        #![proptest_config(proptest_config())]
+
+        /// Apply random operations to a tree and to a `BTreeMap` from sort value to key,
+        /// checking that they agree after every step. Each key `k` is ordered by `values[k]`,
+        /// and swapping two keys in the tree also swaps their values, so that the tree stays
+        /// sorted.
+        #[test]
+        fn matches_btree_map(ops in prop::collection::vec(op(), 0..200)) {
+            let pool: Vec<usize> = (0..NUM_KEYS).collect();
+            let mut values: Vec<usize> = (0..NUM_KEYS).collect();
+            let mut model: BTreeMap<usize, usize> = BTreeMap::new();
+            let mut tree = RedBlackTree::new();
+
+            for op in ops {
+                match op {
+                    Op::Insert(k) => {
+                        if let Entry::Vacant(entry) = model.entry(values[k]) {
+                            tree.insert_by(&pool[k], |a, b| values[*a].cmp(&values[*b]));
+                            entry.insert(k);
+                        }
+                    }
+                    Op::Delete(k) => match tree.get(&pool[k]) {
+                        Some(c) => {
+                            c.delete();
+                            prop_assert_eq!(Some(k), model.remove(&values[k]));
+                        }
+                        None => prop_assert!(!model.contains_key(&values[k])),
+                    },
+                    Op::Swap(a, b) => {
+                        if model.contains_key(&values[a]) && model.contains_key(&values[b]) {
+                            tree.swap(&pool[a], &pool[b]);
+                            values.swap(a, b);
+                            model.insert(values[a], a);
+                            model.insert(values[b], b);
+                        }
+                    }
+                }
+
+                check_tree(&tree);
+                prop_assert_eq!(
+                    model.values().copied().collect::<Vec<_>>(),
+                    tree.iter().copied().collect::<Vec<_>>()
+                );
+                for k in 0..NUM_KEYS {
+                    let found = tree.find_by(|key| values[*key].cmp(&values[k]));
+                    prop_assert_eq!(
+                        model.contains_key(&values[k]),
+                        found.map(|c| *c.key() == k).unwrap_or(false)
+                    );
+                }
+            }
+        }
     }
 }
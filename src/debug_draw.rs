@@ -4,9 +4,10 @@ use svg::node::Text;
 use svg::Document;
 use svg::Node;
 
+use crate::error::Error;
 use crate::line::Line;
 use crate::point::Point;
-use crate::polygon::Polygon;
+use crate::polygon::{FillRule, Polygon};
 use crate::scanlines::{ScanState, SceneEvent};
 use crate::scene::Scene;
 
@@ -20,10 +21,8 @@ const POINT_RADIUS: f64 = 0.15;
 // For SceneEvent renderer.
 const VERTEX_EVENT_FILL: &str = "red";
 const POINTER_FILL: &str = "blue";
-#[allow(unused)]
-const INTERSECTION_START_EVENT_FILL: &str = "purple";
-#[allow(unused)]
-const INTERSECTION_ENG_EVENT_FILL: &str = "orange";
+const INTERSECTION_EVENT_FILL: &str = "purple";
+const ACTIVE_STROKE: &str = "green";
 
 #[derive(Clone)]
 struct Bounds {
@@ -116,7 +115,7 @@ impl DebugDraw {
         })
     }
 
-    pub fn add_point(&mut self, point: Point) -> DebugGroupBuilder<element::Circle> {
+    pub fn add_point(&mut self, point: Point) -> DebugGroupBuilder<'_, element::Circle> {
         let c = element::Circle::new()
             .set("cx", point.x)
             .set("cy", point.y)
@@ -149,16 +148,29 @@ impl DebugDraw {
     fn polygon_to_path(&mut self, poly: &Polygon) -> element::Path {
         let mut data = Data::new();
 
-        data = data.move_to(poly.points[0].coords());
-        self.update_bounds(poly.points[0]);
-        for point in &poly.points[1..] {
-            data = data.line_to(point.coords());
-            self.update_bounds(*point);
+        for ring in poly.rings() {
+            let (first, rest) = match ring.split_first() {
+                Some(split) => split,
+                None => continue,
+            };
+            data = data.move_to(first.coords());
+            self.update_bounds(*first);
+            for point in rest {
+                data = data.line_to(point.coords());
+                self.update_bounds(*point);
+            }
+            data = data.close();
         }
-        data = data.close();
 
         element::Path::new()
             .set("d", data)
+            .set(
+                "fill-rule",
+                match poly.fill_rule {
+                    FillRule::EvenOdd => "evenodd",
+                    FillRule::NonZero => "nonzero",
+                },
+            )
             .set("vector-effect", "non-scaling-stroke")
             .set("stroke-width", STROKE_WIDTH)
             .set("fill", POLY_FILL)
@@ -174,8 +186,8 @@ impl DebugDraw {
     pub fn add_scene<'a>(&'a mut self, scene: &Scene) -> DebugGroupBuilder<'a, element::Group> {
         let mut group = element::Group::new();
 
-        for poly in &scene.polys {
-            let path = self.polygon_to_path(&poly);
+        for poly in scene.polys() {
+            let path = self.polygon_to_path(poly);
             group = group.add(path);
         }
 
@@ -224,11 +236,11 @@ impl DebugDraw {
 
                     g
                 }
-                SceneEvent::IntersectionEvent(_p, _line, _line_event) => {
-                    let _g = element::Group::new();
-                    // g
-                    unimplemented!()
-                }
+                SceneEvent::IntersectionEvent(p, lower, upper) => element::Group::new()
+                    .set("class", "crossing")
+                    .add(self.line(lower))
+                    .add(self.line(upper))
+                    .add(self.point_circle(*p, INTERSECTION_EVENT_FILL)),
             };
 
             queue_group = queue_group.add(g);
@@ -240,11 +252,21 @@ impl DebugDraw {
 
         group = group.add(queue_group);
 
+        // Active lines, from bottom to top.
+        let mut active_group = element::Group::new().set("class", "active");
+        for (i, line) in state.active.iter().enumerate() {
+            let title = element::Title::new().add(Text::new(format!("active[{}]", i)));
+            active_group =
+                active_group.add(self.line(line).set("stroke", ACTIVE_STROKE).add(title));
+        }
+        group = group.add(active_group);
+
         DebugGroupBuilder::new(self, group)
     }
 
-    pub fn save(&mut self, filename: &str) {
-        let bounds = self.bounds.take().expect("No bounds, empty DebugDraw?");
+    /// Write the drawing to an SVG file, with a view box fitted to everything drawn.
+    pub fn save(&self, filename: &str) -> Result<(), Error> {
+        let bounds = self.bounds.as_ref().ok_or(Error::EmptyDrawing)?;
 
         let width = bounds.right - bounds.left;
         let height = bounds.bottom - bounds.top;
@@ -255,9 +277,9 @@ impl DebugDraw {
             width * (1. + 2. * MARGIN),
             height * (1. + 2. * MARGIN)
         );
-        let mut doc = self.doc.take().unwrap();
-        doc = doc.set("viewBox", view_box);
-        svg::save(filename, &doc).expect("Error writing.");
+        let doc = self.doc.clone().unwrap().set("viewBox", view_box);
+        svg::save(filename, &doc)?;
+        Ok(())
     }
 }
 
@@ -266,3 +288,30 @@ impl Default for DebugDraw {
         DebugDraw::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_errors() {
+        assert!(matches!(
+            DebugDraw::new().save("empty.svg"),
+            Err(Error::EmptyDrawing)
+        ));
+
+        let mut d = DebugDraw::new();
+        d.add_point(Point::new(1., 1.));
+        assert!(matches!(
+            d.save("no/such/directory/point.svg"),
+            Err(Error::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_empty_polygon() {
+        let mut d = DebugDraw::new();
+        d.add_poly(&Polygon::new(vec![], 1.));
+        assert!(matches!(d.save("empty.svg"), Err(Error::EmptyDrawing)));
+    }
+}
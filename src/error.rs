@@ -0,0 +1,76 @@
+use crate::point::Point;
+use crate::polygon::PolygonError;
+use std::fmt;
+
+/// Errors returned by the fallible operations of this crate.
+#[derive(Debug)]
+pub enum Error {
+    /// A point has a NaN or infinite coordinate.
+    NonFinitePoint(Point),
+    /// A polygon failed validation.
+    InvalidPolygon(PolygonError),
+    /// A drawing was saved before anything was added to it, so it has no bounds.
+    EmptyDrawing,
+    /// A camera has no well-defined view direction, a degenerate projection or
+    /// a near plane which is not in front of it.
+    InvalidCamera,
+    /// A tolerance for flattening curves is not positive and finite.
+    InvalidTolerance(f64),
+    /// A page size, margin or stroke width for exporting is negative, zero
+    /// (except a margin) or not finite, or the margins leave no room to draw.
+    InvalidPageLayout,
+    /// A face of a mesh refers to a vertex which does not exist.
+    VertexIndexOutOfRange { index: usize, len: usize },
+    /// A file could not be parsed.
+    Parse(String),
+    /// Reading or writing a file failed.
+    Io(std::io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NonFinitePoint(p) => write!(f, "non-finite point {:?}", p),
+            Error::InvalidPolygon(e) => write!(f, "invalid polygon: {}", e),
+            Error::EmptyDrawing => write!(f, "nothing has been drawn"),
+            Error::InvalidCamera => write!(f, "invalid camera"),
+            Error::InvalidTolerance(tolerance) => write!(f, "invalid tolerance {}", tolerance),
+            Error::InvalidPageLayout => write!(f, "invalid page layout"),
+            Error::VertexIndexOutOfRange { index, len } => write!(
+                f,
+                "vertex index {} is out of range for {} vertices",
+                index, len
+            ),
+            Error::Parse(message) => write!(f, "parse error: {}", message),
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::InvalidPolygon(e) => Some(e),
+            Error::NonFinitePoint(_)
+            | Error::EmptyDrawing
+            | Error::InvalidCamera
+            | Error::InvalidTolerance(_)
+            | Error::InvalidPageLayout
+            | Error::VertexIndexOutOfRange { .. }
+            | Error::Parse(_) => None,
+            Error::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<PolygonError> for Error {
+    fn from(e: PolygonError) -> Error {
+        Error::InvalidPolygon(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Error {
+        Error::Io(e)
+    }
+}
@@ -1,9 +1,21 @@
+mod affine;
+mod camera;
 mod data_structures;
 mod debug_draw;
+mod error;
 mod line;
+mod mesh;
 mod point;
+mod point3;
 mod polygon;
+mod predicates;
 pub mod prelude;
 pub mod scanlines;
 mod scene;
+mod scene3d;
+mod svg_export;
+mod svg_import;
 mod vertex;
+mod visibility;
+
+pub use crate::error::Error;
@@ -1,5 +1,8 @@
+use crate::affine::Affine2;
 use crate::point::Point;
-use crate::polygon::Polygon;
+use crate::polygon::PolygonId;
+use crate::predicates::{cross, orient2d};
+use std::cmp::Ordering;
 
 /// Orientation of the line relative to the x axis.
 #[derive(Debug, PartialEq)]
@@ -16,15 +19,16 @@ enum LineSlope {
     InfiniteSlope,
 }
 
-impl LineSlope {
-    /// Expect finite slope.
-    /// TODO: this is a hack until infinite slope is supported.
-    pub fn unwrap(&self) -> f64 {
-        match &self {
-            LineSlope::FiniteSlope(f) => *f,
-            _ => panic!("Unwrapped InfiniteSlope."),
-        }
-    }
+/// The result of intersecting two lines.
+#[derive(Debug, PartialEq)]
+pub enum LineIntersection {
+    /// The lines do not meet.
+    None,
+    /// The lines meet at a single point.
+    Point(Point),
+    /// The lines are collinear and share the segment between the two points,
+    /// the first of which is the smaller.
+    Overlap(Point, Point),
 }
 
 #[derive(Debug)]
@@ -32,7 +36,8 @@ pub struct Line {
     pub start: Point,
     pub end: Point,
     pub orientation: LineOrientation,
-    pub polygon: *const Polygon,
+    /// The polygon this line is an edge of, once the polygon has been added to a scene.
+    pub polygon: Option<PolygonId>,
 }
 
 impl PartialEq for Line {
@@ -45,27 +50,34 @@ impl Eq for Line {}
 
 impl Ord for Line {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.partial_cmp(&other)
-            .expect("Invalid ordering of Lines.")
+        self.cmp_repr().cmp(&other.cmp_repr())
     }
 }
 
 impl PartialOrd for Line {
     fn partial_cmp(&self, other: &Line) -> Option<std::cmp::Ordering> {
-        self.cmp_repr().partial_cmp(&other.cmp_repr())
+        Some(self.cmp(other))
     }
 }
 
 impl Line {
-    fn cmp_repr(&self) -> (Point, Point, *const Polygon) {
+    fn cmp_repr(&self) -> (Point, Point, Option<PolygonId>) {
         (self.start, self.end, self.polygon)
     }
 
     pub fn new(start: Point, end: Point) -> Line {
-        Line::new_with_poly(start, end, std::ptr::null())
+        Line::new_with_polygon_id(start, end, None)
     }
 
-    pub fn new_with_poly(start: Point, end: Point, polygon: *const Polygon) -> Line {
+    pub fn new_with_poly(start: Point, end: Point, polygon: PolygonId) -> Line {
+        Line::new_with_polygon_id(start, end, Some(polygon))
+    }
+
+    pub(crate) fn new_with_polygon_id(
+        start: Point,
+        end: Point,
+        polygon: Option<PolygonId>,
+    ) -> Line {
         if start < end {
             Line {
                 start,
@@ -94,38 +106,123 @@ impl Line {
         }
     }
 
+    /// Returns true if the line is vertical.
+    pub fn is_vertical(&self) -> bool {
+        self.slope() == LineSlope::InfiniteSlope
+    }
+
+    /// Returns the y value of the line (extended in both directions) at `x`. A
+    /// vertical line only has a value at its own x, where the y of its lower end
+    /// (its start) is returned.
     pub fn y_at(&self, x: f64) -> Option<f64> {
         let denom = self.end.x - self.start.x;
         if denom == 0. {
-            None
+            if x == self.start.x {
+                Some(self.start.y)
+            } else {
+                None
+            }
         } else {
             let frac = (x - self.start.x) / denom;
             Some(frac * self.end.y + (1. - frac) * self.start.y)
         }
     }
 
-    pub fn intersect(&self, other: &Line) -> Option<Point> {
-        let self_slope = self.slope().unwrap();
-        let other_slope = other.slope().unwrap();
-        let net_slope = -self_slope + other_slope;
-        let y_delta = self.start.y
-            - other
-                .y_at(self.start.x)
-                .expect("Unhandled vertical line (1).");
-        let x_int = self.start.x + (y_delta / net_slope);
-        if (self.start.x <= x_int)
-            && (x_int <= self.end.x)
-            && (other.start.x <= x_int)
-            && (x_int <= other.end.x)
-        {
-            Some(Point::new(
-                x_int,
-                self.y_at(x_int).expect("Unhandled vertical line (2)."),
-            ))
+    /// Compare the vertical position of this line to `other` just to the right
+    /// of where `other` starts: first by which side of this line `other` starts
+    /// on, then by which side it continues towards. The comparison is exact.
+    pub fn cmp_at_start(&self, other: &Line) -> Ordering {
+        let mut side = orient2d(self.start, self.end, other.start);
+        if side == 0. {
+            side = orient2d(self.start, self.end, other.end);
+        }
+
+        if side > 0. {
+            Ordering::Less
+        } else if side < 0. {
+            Ordering::Greater
         } else {
-            None
+            Ordering::Equal
         }
     }
+
+    /// Returns true if this line has a steeper upward slope than `other`, i.e. if
+    /// this line passes from below `other` to above it where they cross. The
+    /// comparison is exact.
+    pub fn rises_above(&self, other: &Line) -> bool {
+        cross(self.start, self.end, other.start, other.end) < 0.
+    }
+
+    /// Returns where this line meets `other`: nowhere, at a single point, or
+    /// along a shared segment if they are collinear and overlap.
+    ///
+    /// Whether the lines meet is decided exactly. Where one line ends on the
+    /// other, that endpoint is returned exactly; otherwise the crossing point
+    /// is interpolated (along the vertical line, if there is one, so that its
+    /// x value is exact).
+    pub fn intersect(&self, other: &Line) -> LineIntersection {
+        let other_start_side = orient2d(self.start, self.end, other.start);
+        let other_end_side = orient2d(self.start, self.end, other.end);
+
+        if other_start_side == 0. && other_end_side == 0. {
+            let low = self.start.max(other.start);
+            let high = self.end.min(other.end);
+            return match low.cmp(&high) {
+                Ordering::Less => LineIntersection::Overlap(low, high),
+                Ordering::Equal => LineIntersection::Point(low),
+                Ordering::Greater => LineIntersection::None,
+            };
+        }
+
+        let start_side = orient2d(other.start, other.end, self.start);
+        let end_side = orient2d(other.start, other.end, self.end);
+
+        if same_side(other_start_side, other_end_side) || same_side(start_side, end_side) {
+            LineIntersection::None
+        } else if other_start_side == 0. {
+            LineIntersection::Point(other.start)
+        } else if other_end_side == 0. {
+            LineIntersection::Point(other.end)
+        } else if start_side == 0. {
+            LineIntersection::Point(self.start)
+        } else if end_side == 0. {
+            LineIntersection::Point(self.end)
+        } else if other.is_vertical() {
+            let t = other_start_side / (other_start_side - other_end_side);
+            LineIntersection::Point(other.start.lerp(other.end, t))
+        } else {
+            let t = start_side / (start_side - end_side);
+            LineIntersection::Point(self.start.lerp(self.end, t))
+        }
+    }
+
+    /// Returns true if this line and `other` lie on the same infinite line.
+    pub fn collinear(&self, other: &Line) -> bool {
+        orient2d(self.start, self.end, other.start) == 0.
+            && orient2d(self.start, self.end, other.end) == 0.
+    }
+
+    /// Returns the length of the line.
+    pub fn length(&self) -> f64 {
+        self.start.distance(self.end)
+    }
+
+    /// Returns the image of this line under `transform`, belonging to the same
+    /// polygon. The ends are reordered so that start is still left of end, and
+    /// the orientation is updated to match, e.g. after a reflection.
+    pub fn transform(&self, transform: &Affine2) -> Line {
+        let (from, to) = match self.orientation {
+            LineOrientation::LeftToRight => (self.start, self.end),
+            LineOrientation::RightToLeft => (self.end, self.start),
+        };
+        Line::new_with_polygon_id(transform.apply(from), transform.apply(to), self.polygon)
+    }
+}
+
+/// Returns true if two orientations are both strictly positive or both
+/// strictly negative.
+fn same_side(a: f64, b: f64) -> bool {
+    (a > 0. && b > 0.) || (a < 0. && b < 0.)
 }
 
 #[cfg(test)]
@@ -195,7 +292,7 @@ mod tests {
 
         let l1 = Line::new(p1, p2);
         assert_eq!(None, l1.y_at(4.));
-        assert_eq!(None, l1.y_at(5.)); // Behavior TBD.
+        assert_eq!(Some(3.), l1.y_at(5.));
         assert_eq!(None, l1.y_at(6.));
     }
 
@@ -204,7 +301,181 @@ mod tests {
         let l1 = Line::new(Point::new(4., 10.), Point::new(14., 20.));
         let l2 = Line::new(Point::new(0., 20.), Point::new(20., 0.));
 
-        assert_eq!(Some(Point::new(7., 13.)), l1.intersect(&l2));
-        assert_eq!(Some(Point::new(7., 13.)), l2.intersect(&l1));
+        assert_eq!(
+            LineIntersection::Point(Point::new(7., 13.)),
+            l1.intersect(&l2)
+        );
+        assert_eq!(
+            LineIntersection::Point(Point::new(7., 13.)),
+            l2.intersect(&l1)
+        );
+    }
+
+    #[test]
+    fn test_intersect_vertical_vertical() {
+        let l1 = Line::new(Point::new(5., 0.), Point::new(5., 10.));
+
+        let l2 = Line::new(Point::new(5., 15.), Point::new(5., 4.));
+        let overlap = LineIntersection::Overlap(Point::new(5., 4.), Point::new(5., 10.));
+        assert_eq!(overlap, l1.intersect(&l2));
+        assert_eq!(overlap, l2.intersect(&l1));
+
+        // Touching at an endpoint.
+        let l3 = Line::new(Point::new(5., 10.), Point::new(5., 12.));
+        assert_eq!(
+            LineIntersection::Point(Point::new(5., 10.)),
+            l1.intersect(&l3)
+        );
+
+        // Same x, but disjoint.
+        let l4 = Line::new(Point::new(5., 11.), Point::new(5., 12.));
+        assert_eq!(LineIntersection::None, l1.intersect(&l4));
+
+        // Parallel.
+        let l5 = Line::new(Point::new(6., 0.), Point::new(6., 10.));
+        assert_eq!(LineIntersection::None, l1.intersect(&l5));
+    }
+
+    #[test]
+    fn test_intersect_vertical_sloped() {
+        let vertical = Line::new(Point::new(5., 0.), Point::new(5., 10.));
+
+        let crossing = Line::new(Point::new(0., 0.), Point::new(10., 10.));
+        assert_eq!(
+            LineIntersection::Point(Point::new(5., 5.)),
+            vertical.intersect(&crossing)
+        );
+        assert_eq!(
+            LineIntersection::Point(Point::new(5., 5.)),
+            crossing.intersect(&vertical)
+        );
+
+        let horizontal = Line::new(Point::new(5., 10.), Point::new(8., 10.));
+        assert_eq!(
+            LineIntersection::Point(Point::new(5., 10.)),
+            vertical.intersect(&horizontal)
+        );
+
+        // Passes above the vertical line.
+        let above = Line::new(Point::new(0., 10.), Point::new(10., 20.));
+        assert_eq!(LineIntersection::None, vertical.intersect(&above));
+
+        // Ends before reaching the vertical line's x.
+        let short = Line::new(Point::new(0., 0.), Point::new(4., 4.));
+        assert_eq!(LineIntersection::None, vertical.intersect(&short));
+    }
+
+    #[test]
+    fn test_intersect_collinear() {
+        let l1 = Line::new(Point::new(0., 0.), Point::new(10., 5.));
+
+        let overlapping = Line::new(Point::new(4., 2.), Point::new(14., 7.));
+        let overlap = LineIntersection::Overlap(Point::new(4., 2.), Point::new(10., 5.));
+        assert_eq!(overlap, l1.intersect(&overlapping));
+        assert_eq!(overlap, overlapping.intersect(&l1));
+
+        let contained = Line::new(Point::new(2., 1.), Point::new(6., 3.));
+        let overlap = LineIntersection::Overlap(Point::new(2., 1.), Point::new(6., 3.));
+        assert_eq!(overlap, l1.intersect(&contained));
+
+        let touching = Line::new(Point::new(10., 5.), Point::new(12., 6.));
+        assert_eq!(
+            LineIntersection::Point(Point::new(10., 5.)),
+            l1.intersect(&touching)
+        );
+        assert!(l1.collinear(&touching));
+
+        let disjoint = Line::new(Point::new(12., 6.), Point::new(14., 7.));
+        assert_eq!(LineIntersection::None, l1.intersect(&disjoint));
+
+        let parallel = Line::new(Point::new(0., 1.), Point::new(10., 6.));
+        assert_eq!(LineIntersection::None, l1.intersect(&parallel));
+        assert!(!l1.collinear(&parallel));
+    }
+
+    #[test]
+    fn test_cmp_at_start() {
+        let l1 = Line::new(Point::new(0., 0.), Point::new(10., 10.));
+
+        let above = Line::new(Point::new(5., 6.), Point::new(10., 6.));
+        assert_eq!(Ordering::Less, l1.cmp_at_start(&above));
+
+        let below = Line::new(Point::new(5., 4.), Point::new(10., 20.));
+        assert_eq!(Ordering::Greater, l1.cmp_at_start(&below));
+
+        // Lines starting on this line are ordered by where they go next.
+        let steeper = Line::new(Point::new(5., 5.), Point::new(6., 7.));
+        assert_eq!(Ordering::Less, l1.cmp_at_start(&steeper));
+        let shallower = Line::new(Point::new(5., 5.), Point::new(6., 5.5));
+        assert_eq!(Ordering::Greater, l1.cmp_at_start(&shallower));
+        let collinear = Line::new(Point::new(5., 5.), Point::new(20., 20.));
+        assert_eq!(Ordering::Equal, l1.cmp_at_start(&collinear));
+    }
+
+    #[test]
+    fn test_rises_above() {
+        let vertical = Line::new(Point::new(5., 0.), Point::new(5., 10.));
+        let sloped = Line::new(Point::new(5., 0.), Point::new(10., 10.));
+        let flat = Line::new(Point::new(0., 3.), Point::new(10., 3.));
+
+        assert!(vertical.rises_above(&sloped));
+        assert!(!sloped.rises_above(&vertical));
+        assert!(sloped.rises_above(&flat));
+        assert!(!flat.rises_above(&flat));
+    }
+
+    #[test]
+    fn test_intersect_nearly_parallel() {
+        // Lines which differ in slope by a few ulps still cross exactly once,
+        // in both argument orders.
+        let l1 = Line::new(Point::new(0., 0.), Point::new(1., 1.));
+        let l2 = Line::new(Point::new(0., 1e-17), Point::new(1., 1. - f64::EPSILON));
+        for intersection in &[l1.intersect(&l2), l2.intersect(&l1)] {
+            match intersection {
+                LineIntersection::Point(p) => assert!((0. ..=1.).contains(&p.x)),
+                _ => panic!("Expected a single crossing, got {:?}", intersection),
+            }
+        }
+
+        // Exactly parallel lines never meet.
+        let l3 = Line::new(Point::new(0., 0.5), Point::new(2., 2.5));
+        assert_eq!(LineIntersection::None, l1.intersect(&l3));
+    }
+
+    #[test]
+    fn test_length() {
+        assert_eq!(
+            5.,
+            Line::new(Point::new(4., 5.), Point::new(1., 1.)).length()
+        );
+        assert_eq!(
+            2.,
+            Line::new(Point::new(1., 1.), Point::new(1., 3.)).length()
+        );
+    }
+
+    #[test]
+    fn test_transform() {
+        let p1 = Point::new(1., 2.);
+        let p2 = Point::new(4., 3.);
+        let line = Line::new_with_poly(p1, p2, PolygonId(3));
+
+        let moved = line.transform(&Affine2::translate(1., 1.));
+        assert_eq!(Point::new(2., 3.), moved.start);
+        assert_eq!(Point::new(5., 4.), moved.end);
+        assert_eq!(LineOrientation::LeftToRight, moved.orientation);
+        assert_eq!(Some(PolygonId(3)), moved.polygon);
+
+        // Reflecting in the y axis swaps the ends, so the line now runs from
+        // right to left.
+        let reflected = line.transform(&Affine2::scale(-1., 1.));
+        assert_eq!(Point::new(-4., 3.), reflected.start);
+        assert_eq!(Point::new(-1., 2.), reflected.end);
+        assert_eq!(LineOrientation::RightToLeft, reflected.orientation);
+
+        // Reflecting in the x axis keeps the ends in order.
+        let reflected = Line::new(p2, p1).transform(&Affine2::scale(1., -1.));
+        assert_eq!(Point::new(1., -2.), reflected.start);
+        assert_eq!(LineOrientation::RightToLeft, reflected.orientation);
     }
 }
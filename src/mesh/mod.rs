@@ -0,0 +1,147 @@
+mod obj;
+mod stl;
+
+use crate::error::Error;
+use crate::point3::Point3;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+/// A mesh of planar faces, each given as a list of indices into the vertices,
+/// as read from a model file. Faces can be added to a `Scene3D` with
+/// `Scene3D::add_mesh`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Mesh {
+    pub vertices: Vec<Point3>,
+    pub faces: Vec<Vec<usize>>,
+}
+
+impl Mesh {
+    pub fn new() -> Mesh {
+        Mesh::default()
+    }
+
+    /// Read a mesh in Wavefront OBJ format. Only vertex positions and faces
+    /// are used; faces may have any number of vertices, and indices may count
+    /// back from the most recent vertex. Degenerate faces are removed.
+    pub fn read_obj<R: Read>(reader: R) -> Result<Mesh, Error> {
+        let mut mesh = obj::read(BufReader::new(reader))?;
+        mesh.remove_degenerate_faces();
+        Ok(mesh)
+    }
+
+    /// Read a mesh in STL format, either ASCII or binary. Vertices which are
+    /// repeated between triangles are shared, and degenerate triangles are
+    /// removed.
+    pub fn read_stl<R: Read>(mut reader: R) -> Result<Mesh, Error> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut mesh = stl::read(&data)?;
+        mesh.remove_degenerate_faces();
+        Ok(mesh)
+    }
+
+    /// Read a mesh from an OBJ file. See `read_obj`.
+    pub fn load_obj<P: AsRef<Path>>(path: P) -> Result<Mesh, Error> {
+        Mesh::read_obj(File::open(path)?)
+    }
+
+    /// Read a mesh from an STL file. See `read_stl`.
+    pub fn load_stl<P: AsRef<Path>>(path: P) -> Result<Mesh, Error> {
+        Mesh::read_stl(File::open(path)?)
+    }
+
+    /// Remove repeated vertices from each face, then remove the faces which
+    /// are left with fewer than three vertices or which enclose no area.
+    pub fn remove_degenerate_faces(&mut self) {
+        let vertices = &self.vertices;
+        for face in &mut self.faces {
+            face.dedup();
+            while face.len() > 1 && face.first() == face.last() {
+                face.pop();
+            }
+        }
+        self.faces
+            .retain(|face| face.len() >= 3 && area_vector(vertices, face).length() > 0.);
+    }
+}
+
+/// Returns a vector normal to the face whose length is twice its area, found
+/// with Newell's method so that non-convex faces are handled.
+fn area_vector(vertices: &[Point3], face: &[usize]) -> Point3 {
+    let mut normal = Point3::new(0., 0., 0.);
+    let mut last = vertices[face[face.len() - 1]];
+    for &index in face {
+        let point = vertices[index];
+        normal = normal + last.cross(point);
+        last = point;
+    }
+    normal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    #[test]
+    fn test_remove_degenerate_faces() {
+        let mut mesh = Mesh {
+            vertices: vec![
+                Point3::new(0., 0., 0.),
+                Point3::new(1., 0., 0.),
+                Point3::new(0., 1., 0.),
+                Point3::new(2., 0., 0.),
+            ],
+            faces: vec![
+                vec![0, 1, 2],
+                vec![0, 1, 1, 2, 0],
+                vec![0, 1, 0],
+                vec![0, 1, 3],
+            ],
+        };
+        mesh.remove_degenerate_faces();
+
+        assert_eq!(vec![vec![0, 1, 2], vec![0, 1, 2]], mesh.faces);
+    }
+
+    #[test]
+    fn test_load_obj() {
+        let mesh = Mesh::load_obj(fixture("cube.obj")).unwrap();
+
+        assert_eq!(8, mesh.vertices.len());
+        assert_eq!(Point3::new(1., 1., 1.), mesh.vertices[6]);
+        // The cube has six quads; the degenerate face is dropped, and the
+        // faces using relative indices are the same as the others.
+        assert_eq!(6, mesh.faces.len());
+        assert_eq!(vec![0, 1, 2, 3], mesh.faces[0]);
+        assert_eq!(vec![4, 7, 6, 5], mesh.faces[1]);
+        assert_eq!(vec![3, 2, 6, 7], mesh.faces[5]);
+    }
+
+    #[test]
+    fn test_load_stl() {
+        for name in &["tetrahedron_ascii.stl", "tetrahedron_binary.stl"] {
+            let mesh = Mesh::load_stl(fixture(name)).unwrap();
+
+            // The tetrahedron's triangles share their four vertices, and the
+            // degenerate fifth triangle is dropped.
+            assert_eq!(4, mesh.vertices.len(), "{}", name);
+            assert_eq!(4, mesh.faces.len(), "{}", name);
+            assert_eq!(Point3::new(0., 0., 0.), mesh.vertices[0]);
+            assert_eq!(Point3::new(0., 0., 1.), mesh.vertices[3]);
+            assert_eq!(vec![0, 1, 2], mesh.faces[0]);
+        }
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        assert!(matches!(
+            Mesh::load_obj(fixture("missing.obj")),
+            Err(Error::Io(_))
+        ));
+    }
+}
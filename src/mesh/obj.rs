@@ -0,0 +1,96 @@
+use super::Mesh;
+use crate::error::Error;
+use crate::point3::Point3;
+use std::io::BufRead;
+
+/// Read the vertex positions and faces of an OBJ file, ignoring everything else.
+pub(crate) fn read<R: BufRead>(reader: R) -> Result<Mesh, Error> {
+    let mut mesh = Mesh::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let parse_error =
+            |message: String| Error::Parse(format!("line {}: {}", number + 1, message));
+
+        let line = match line.find('#') {
+            Some(comment) => &line[..comment],
+            None => &line[..],
+        };
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => {
+                let mut coords = [0.; 3];
+                for coord in &mut coords {
+                    *coord = tokens
+                        .next()
+                        .and_then(|token| token.parse().ok())
+                        .ok_or_else(|| parse_error(format!("invalid vertex {:?}", line)))?;
+                }
+                mesh.vertices
+                    .push(Point3::new(coords[0], coords[1], coords[2]));
+            }
+            Some("f") => {
+                let face = tokens
+                    .map(|token| vertex_index(token, mesh.vertices.len()))
+                    .collect::<Option<Vec<usize>>>()
+                    .ok_or_else(|| parse_error(format!("invalid face {:?}", line)))?;
+                mesh.faces.push(face);
+            }
+            _ => (),
+        }
+    }
+    Ok(mesh)
+}
+
+/// Returns the zero-based index of the vertex referred to by a face token such
+/// as `3`, `3/1` or `3//2`, given the number of vertices read so far. Indices
+/// start at one, and negative indices count back from the last vertex read.
+fn vertex_index(token: &str, vertex_count: usize) -> Option<usize> {
+    let index: i64 = token.split('/').next()?.parse().ok()?;
+    let index = if index > 0 {
+        index - 1
+    } else if index < 0 {
+        vertex_count as i64 + index
+    } else {
+        return None;
+    };
+
+    if 0 <= index && index < vertex_count as i64 {
+        Some(index as usize)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vertex_index() {
+        assert_eq!(Some(0), vertex_index("1", 3));
+        assert_eq!(Some(2), vertex_index("3/1", 3));
+        assert_eq!(Some(1), vertex_index("2//5", 3));
+        assert_eq!(Some(2), vertex_index("-1/2/3", 3));
+        assert_eq!(Some(0), vertex_index("-3", 3));
+
+        assert_eq!(None, vertex_index("0", 3));
+        assert_eq!(None, vertex_index("4", 3));
+        assert_eq!(None, vertex_index("-4", 3));
+        assert_eq!(None, vertex_index("x", 3));
+    }
+
+    #[test]
+    fn test_read_errors() {
+        let read_str = |s: &str| read(s.as_bytes());
+
+        assert!(read_str("v 1 2 3\nv 1 2 3\nv 1 2 3\nf 1 2 3\n").is_ok());
+        assert!(matches!(
+            read_str("v 1 2\n"),
+            Err(Error::Parse(message)) if message.starts_with("line 1:")
+        ));
+        assert!(matches!(
+            read_str("v 1 2 3\n\nf 1 2 3\n"),
+            Err(Error::Parse(message)) if message.starts_with("line 3:")
+        ));
+    }
+}
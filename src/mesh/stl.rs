@@ -0,0 +1,168 @@
+use super::Mesh;
+use crate::error::Error;
+use crate::point3::Point3;
+use std::collections::HashMap;
+use std::convert::TryInto;
+
+const HEADER_LEN: usize = 80;
+const TRIANGLE_LEN: usize = 50;
+
+/// Read an STL file, which is binary unless it starts with `solid` and isn't
+/// exactly the length that its binary header says it should be. (Some binary
+/// files also start with `solid`.)
+pub(crate) fn read(data: &[u8]) -> Result<Mesh, Error> {
+    let binary_len = data
+        .get(HEADER_LEN..HEADER_LEN + 4)
+        .map(|count| HEADER_LEN + 4 + TRIANGLE_LEN * u32_at(count) as usize);
+    if data.starts_with(b"solid") && binary_len != Some(data.len()) {
+        read_ascii(data)
+    } else {
+        read_binary(data)
+    }
+}
+
+fn u32_at(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes[..4].try_into().unwrap())
+}
+
+fn f32_at(bytes: &[u8]) -> f32 {
+    f32::from_le_bytes(bytes[..4].try_into().unwrap())
+}
+
+fn read_binary(data: &[u8]) -> Result<Mesh, Error> {
+    let count = match data.get(HEADER_LEN..HEADER_LEN + 4) {
+        Some(count) => u32_at(count) as usize,
+        None => return Err(Error::Parse("STL file is too short".to_string())),
+    };
+    let triangles = &data[HEADER_LEN + 4..];
+    if triangles.len() != count * TRIANGLE_LEN {
+        return Err(Error::Parse(format!(
+            "STL file has {} bytes of triangles, but should have {} triangles",
+            triangles.len(),
+            count
+        )));
+    }
+
+    let mut builder = MeshBuilder::default();
+    for triangle in triangles.chunks(TRIANGLE_LEN) {
+        // Skip the normal, which is recomputed from the vertices when needed.
+        let points: Vec<Point3> = triangle[12..48]
+            .chunks(12)
+            .map(|v| {
+                Point3::new(
+                    f32_at(&v[0..]) as f64,
+                    f32_at(&v[4..]) as f64,
+                    f32_at(&v[8..]) as f64,
+                )
+            })
+            .collect();
+        builder.add_face(&points);
+    }
+    Ok(builder.mesh)
+}
+
+fn read_ascii(data: &[u8]) -> Result<Mesh, Error> {
+    let text = std::str::from_utf8(data)
+        .map_err(|_| Error::Parse("ASCII STL file is not valid UTF-8".to_string()))?;
+
+    let mut builder = MeshBuilder::default();
+    let mut points = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("vertex") => {
+                // A token which is not a number invalidates the whole vertex.
+                let coords: Vec<f64> = tokens
+                    .map(|token| token.parse())
+                    .collect::<Result<_, _>>()
+                    .unwrap_or_default();
+                if coords.len() != 3 {
+                    return Err(Error::Parse(format!(
+                        "line {}: invalid vertex {:?}",
+                        number + 1,
+                        line.trim()
+                    )));
+                }
+                points.push(Point3::new(coords[0], coords[1], coords[2]));
+            }
+            Some("endloop") => {
+                builder.add_face(&points);
+                points.clear();
+            }
+            _ => (),
+        }
+    }
+    Ok(builder.mesh)
+}
+
+/// Builds a mesh from faces given by their points, sharing identical vertices.
+#[derive(Default)]
+struct MeshBuilder {
+    mesh: Mesh,
+    indices: HashMap<[u64; 3], usize>,
+}
+
+impl MeshBuilder {
+    fn add_face(&mut self, points: &[Point3]) {
+        let MeshBuilder { mesh, indices } = self;
+        let face = points
+            .iter()
+            .map(|&point| {
+                // Adding zero turns -0.0 into 0.0, so that they are the same vertex.
+                let key = [
+                    (point.x + 0.).to_bits(),
+                    (point.y + 0.).to_bits(),
+                    (point.z + 0.).to_bits(),
+                ];
+                *indices.entry(key).or_insert_with(|| {
+                    mesh.vertices.push(point);
+                    mesh.vertices.len() - 1
+                })
+            })
+            .collect();
+        mesh.faces.push(face);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_errors() {
+        assert!(matches!(read(b"short"), Err(Error::Parse(_))));
+        assert_eq!(Mesh::new(), read(b"solid empty\nendsolid empty\n").unwrap());
+        assert!(matches!(
+            read(b"solid x\nfacet normal 0 0 1\nouter loop\nvertex 1 2\n"),
+            Err(Error::Parse(message)) if message.starts_with("line 4:")
+        ));
+        assert!(matches!(
+            read(b"solid x\nfacet normal 0 0 1\nouter loop\nvertex 1 2 x 3\n"),
+            Err(Error::Parse(message)) if message.starts_with("line 4:")
+        ));
+
+        // A binary file which claims more triangles than it has.
+        let mut data = vec![0; HEADER_LEN];
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&[0; TRIANGLE_LEN]);
+        assert!(matches!(read(&data), Err(Error::Parse(_))));
+    }
+
+    #[test]
+    fn test_binary_starting_with_solid() {
+        let mut data = b"solid".to_vec();
+        data.resize(HEADER_LEN, 0);
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&[0; 12]);
+        for coords in &[[0f32, 0., 0.], [1., 0., 0.], [0., 1., 0.]] {
+            for coord in coords {
+                data.extend_from_slice(&coord.to_le_bytes());
+            }
+        }
+        data.extend_from_slice(&[0; 2]);
+
+        let mesh = read(&data).unwrap();
+        assert_eq!(3, mesh.vertices.len());
+        assert_eq!(vec![vec![0, 1, 2]], mesh.faces);
+    }
+}
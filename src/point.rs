@@ -1,7 +1,23 @@
-use std::cmp::PartialOrd;
+use crate::affine::Affine2;
+use crate::error::Error;
+use std::cmp::Ordering;
+use std::ops::{Add, Mul, Neg, Sub};
 
 /// Represents a point on the plane.
-#[derive(PartialEq, Debug, Copy, Clone)]
+///
+/// `Point::new` and the public fields accept any value, including NaN and
+/// infinity. `Point::try_new` is the validated constructor, which rejects
+/// non-finite coordinates, and should be used for untrusted input. Polygons
+/// are checked for non-finite points when they are validated, which happens
+/// when they are added to a scene or a scene is transformed.
+///
+/// Points are totally ordered, first by `x` and then by `y`, so that they can
+/// be used as keys in ordered collections. Equality agrees with the ordering.
+/// `-0.0` and `0.0` are treated as the same value, as they are by `f64`'s own
+/// comparisons. Negative infinity comes before every finite value, and positive
+/// infinity after them, followed by NaN; every NaN is treated as the same
+/// value, whatever its sign or payload.
+#[derive(Debug, Copy, Clone)]
 pub struct Point {
     pub x: f64,
     pub y: f64,
@@ -12,23 +28,128 @@ impl Point {
         Point { x, y }
     }
 
+    /// Construct a point, or return an error if either coordinate is NaN or infinite.
+    pub fn try_new(x: f64, y: f64) -> Result<Point, Error> {
+        let point = Point { x, y };
+        if point.is_finite() {
+            Ok(point)
+        } else {
+            Err(Error::NonFinitePoint(point))
+        }
+    }
+
+    /// Returns true if neither coordinate is NaN or infinite.
+    pub fn is_finite(&self) -> bool {
+        self.x.is_finite() && self.y.is_finite()
+    }
+
     pub fn coords(&self) -> (f64, f64) {
         (self.x, self.y)
     }
+
+    /// Returns the dot product of this point and `other`, treated as vectors.
+    pub fn dot(self, other: Point) -> f64 {
+        self.x * other.x + self.y * other.y
+    }
+
+    /// Returns the z component of the cross product of this point and `other`,
+    /// treated as vectors. It is positive if `other` points counterclockwise
+    /// of this point. The result is not exact; see `predicates::cross`.
+    pub fn cross(self, other: Point) -> f64 {
+        self.x * other.y - self.y * other.x
+    }
+
+    /// Returns the distance of this point from the origin.
+    pub fn length(self) -> f64 {
+        self.x.hypot(self.y)
+    }
+
+    /// Returns the distance between this point and `other`.
+    pub fn distance(self, other: Point) -> f64 {
+        (other - self).length()
+    }
+
+    /// Returns the point a fraction `t` of the way from this point to `other`.
+    pub fn lerp(self, other: Point, t: f64) -> Point {
+        self + (other - self) * t
+    }
+
+    /// Returns this point rotated counterclockwise by `angle` radians about `origin`.
+    pub fn rotate_about(self, origin: Point, angle: f64) -> Point {
+        let (sin, cos) = angle.sin_cos();
+        let d = self - origin;
+        origin + Point::new(d.x * cos - d.y * sin, d.x * sin + d.y * cos)
+    }
+
+    /// Returns true if each coordinate of this point is within `epsilon` of the
+    /// corresponding coordinate of `other`.
+    pub fn approx_eq(self, other: Point, epsilon: f64) -> bool {
+        (self.x - other.x).abs() <= epsilon && (self.y - other.y).abs() <= epsilon
+    }
+
+    /// Returns the image of this point under `transform`.
+    pub fn transform(self, transform: &Affine2) -> Point {
+        transform.apply(self)
+    }
 }
 
-impl PartialOrd for Point {
-    fn partial_cmp(&self, other: &Point) -> Option<std::cmp::Ordering> {
-        (self.x, self.y).partial_cmp(&(other.x, other.y))
+impl Add for Point {
+    type Output = Point;
+
+    fn add(self, other: Point) -> Point {
+        Point::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl Sub for Point {
+    type Output = Point;
+
+    fn sub(self, other: Point) -> Point {
+        Point::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl Mul<f64> for Point {
+    type Output = Point;
+
+    fn mul(self, scale: f64) -> Point {
+        Point::new(self.x * scale, self.y * scale)
+    }
+}
+
+impl Neg for Point {
+    type Output = Point;
+
+    fn neg(self) -> Point {
+        Point::new(-self.x, -self.y)
+    }
+}
+
+/// Compare two coordinates with `f64::total_cmp`, treating `-0.0` as `0.0` and
+/// every NaN as `f64::NAN`, which `total_cmp` puts after positive infinity.
+fn cmp_coord(a: f64, b: f64) -> Ordering {
+    // Adding zero turns -0.0 into 0.0 and leaves every other value unchanged.
+    let canonical = |v: f64| if v.is_nan() { f64::NAN } else { v + 0. };
+    canonical(a).total_cmp(&canonical(b))
+}
+
+impl PartialEq for Point {
+    fn eq(&self, other: &Point) -> bool {
+        self.cmp(other) == Ordering::Equal
     }
 }
 
 impl Eq for Point {}
 
-impl std::cmp::Ord for Point {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.partial_cmp(other)
-            .expect("Invalid values encountered in point comparison.")
+impl PartialOrd for Point {
+    fn partial_cmp(&self, other: &Point) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Point {
+    fn cmp(&self, other: &Self) -> Ordering {
+        cmp_coord(self.x, other.x).then_with(|| cmp_coord(self.y, other.y))
     }
 }
 
@@ -38,10 +159,117 @@ mod tests {
 
     #[test]
     fn test_lt() {
-        assert_eq!(true, Point::new(4., 5.) < Point::new(5., 5.));
-        assert_eq!(true, Point::new(4., 4.) < Point::new(4., 5.));
-        assert_eq!(false, Point::new(5., 5.) < Point::new(5., 5.));
-        assert_eq!(false, Point::new(4., 4.) < Point::new(4., 3.));
-        assert_eq!(false, Point::new(4., 4.) < Point::new(3., 6.));
+        assert!(Point::new(4., 5.) < Point::new(5., 5.));
+        assert!(Point::new(4., 4.) < Point::new(4., 5.));
+        assert!(Point::new(5., 5.) >= Point::new(5., 5.));
+        assert!(Point::new(4., 4.) >= Point::new(4., 3.));
+        assert!(Point::new(4., 4.) >= Point::new(3., 6.));
+    }
+
+    #[test]
+    fn test_signed_zero() {
+        assert_eq!(Point::new(0., -0.), Point::new(-0., 0.));
+        assert_eq!(
+            Ordering::Equal,
+            Point::new(0., -0.).cmp(&Point::new(-0., 0.))
+        );
+        assert!(Point::new(-0., 1.) < Point::new(0., 2.));
+    }
+
+    #[test]
+    fn test_non_finite_order() {
+        let nan = Point::new(f64::NAN, 0.);
+        let inf = Point::new(f64::INFINITY, 0.);
+        let neg_inf = Point::new(f64::NEG_INFINITY, 0.);
+        let finite = Point::new(1e300, 0.);
+
+        assert!(neg_inf < finite);
+        assert!(finite < inf);
+        assert!(inf < nan);
+        assert_eq!(nan, nan);
+
+        // A NaN with its sign bit set, or with another payload, is the same as
+        // any other NaN.
+        let neg_nan = Point::new(-f64::NAN, 0.);
+        let other_nan = Point::new(f64::from_bits(f64::NAN.to_bits() | 1), 0.);
+        assert!(neg_nan.x.is_sign_negative());
+        assert!(inf < neg_nan);
+        assert_eq!(nan, neg_nan);
+        assert_eq!(nan, other_nan);
+
+        let mut points = vec![nan, finite, neg_nan, inf, neg_inf];
+        points.sort();
+        assert_eq!(vec![neg_inf, finite, inf, nan, nan], points);
+    }
+
+    #[test]
+    fn test_try_new() {
+        assert_eq!(Point::new(1., 2.), Point::try_new(1., 2.).unwrap());
+        assert!(Point::try_new(f64::NAN, 2.).is_err());
+        assert!(Point::try_new(1., f64::INFINITY).is_err());
+        assert!(Point::try_new(f64::NEG_INFINITY, 2.).is_err());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let a = Point::new(1., 2.);
+        let b = Point::new(4., -2.);
+
+        assert_eq!(Point::new(5., 0.), a + b);
+        assert_eq!(Point::new(3., -4.), b - a);
+        assert_eq!(Point::new(2., 4.), a * 2.);
+        assert_eq!(Point::new(-1., -2.), -a);
+    }
+
+    #[test]
+    fn test_products() {
+        let a = Point::new(1., 2.);
+        let b = Point::new(4., -2.);
+
+        assert_eq!(0., a.dot(b));
+        assert_eq!(-10., a.cross(b));
+        assert_eq!(10., b.cross(a));
+        assert_eq!(0., a.cross(a * 3.));
+    }
+
+    #[test]
+    fn test_length_and_distance() {
+        assert_eq!(5., Point::new(3., 4.).length());
+        assert_eq!(5., Point::new(1., 1.).distance(Point::new(4., 5.)));
+        assert_eq!(0., Point::new(1., 1.).distance(Point::new(1., 1.)));
+    }
+
+    #[test]
+    fn test_lerp() {
+        let a = Point::new(0., 10.);
+        let b = Point::new(10., 20.);
+
+        assert_eq!(a, a.lerp(b, 0.));
+        assert_eq!(b, a.lerp(b, 1.));
+        assert_eq!(Point::new(2.5, 12.5), a.lerp(b, 0.25));
+    }
+
+    #[test]
+    fn test_rotate_about() {
+        let origin = Point::new(1., 1.);
+        let p = Point::new(2., 1.);
+
+        let quarter = p.rotate_about(origin, std::f64::consts::FRAC_PI_2);
+        assert!(quarter.approx_eq(Point::new(1., 2.), 1e-12));
+
+        let half = p.rotate_about(origin, std::f64::consts::PI);
+        assert!(half.approx_eq(Point::new(0., 1.), 1e-12));
+
+        assert_eq!(origin, origin.rotate_about(origin, 1.));
+    }
+
+    #[test]
+    fn test_approx_eq() {
+        let a = Point::new(1., 1.);
+
+        assert!(a.approx_eq(Point::new(1.05, 0.95), 0.1));
+        assert!(!a.approx_eq(Point::new(1.2, 1.), 0.1));
+        assert!(!a.approx_eq(Point::new(1., 0.8), 0.1));
+        assert!(!a.approx_eq(Point::new(f64::NAN, 1.), 0.1));
     }
 }
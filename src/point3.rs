@@ -0,0 +1,125 @@
+use std::ops::{Add, Mul, Neg, Sub};
+
+/// Represents a point in space, or a vector from the origin to it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Point3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Point3 {
+    pub fn new(x: f64, y: f64, z: f64) -> Point3 {
+        Point3 { x, y, z }
+    }
+
+    /// Returns true if no coordinate is NaN or infinite.
+    pub fn is_finite(&self) -> bool {
+        self.x.is_finite() && self.y.is_finite() && self.z.is_finite()
+    }
+
+    /// Returns the dot product of this point and `other`, treated as vectors.
+    pub fn dot(self, other: Point3) -> f64 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    /// Returns the cross product of this point and `other`, treated as vectors.
+    pub fn cross(self, other: Point3) -> Point3 {
+        Point3::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    /// Returns the distance of this point from the origin.
+    pub fn length(self) -> f64 {
+        self.dot(self).sqrt()
+    }
+
+    /// Returns the vector of length one in the same direction as this one, or
+    /// `None` if this is the zero vector.
+    pub fn normalize(self) -> Option<Point3> {
+        let length = self.length();
+        if length > 0. && length.is_finite() {
+            Some(self * (1. / length))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the point a fraction `t` of the way from this point to `other`.
+    pub fn lerp(self, other: Point3, t: f64) -> Point3 {
+        self + (other - self) * t
+    }
+}
+
+impl Add for Point3 {
+    type Output = Point3;
+
+    fn add(self, other: Point3) -> Point3 {
+        Point3::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl Sub for Point3 {
+    type Output = Point3;
+
+    fn sub(self, other: Point3) -> Point3 {
+        Point3::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl Mul<f64> for Point3 {
+    type Output = Point3;
+
+    fn mul(self, factor: f64) -> Point3 {
+        Point3::new(self.x * factor, self.y * factor, self.z * factor)
+    }
+}
+
+impl Neg for Point3 {
+    type Output = Point3;
+
+    fn neg(self) -> Point3 {
+        Point3::new(-self.x, -self.y, -self.z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_products() {
+        let x = Point3::new(1., 0., 0.);
+        let y = Point3::new(0., 1., 0.);
+
+        assert_eq!(0., x.dot(y));
+        assert_eq!(14., Point3::new(1., 2., 3.).dot(Point3::new(1., 2., 3.)));
+        assert_eq!(Point3::new(0., 0., 1.), x.cross(y));
+        assert_eq!(Point3::new(0., 0., -1.), y.cross(x));
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            Some(Point3::new(0., -1., 0.)),
+            Point3::new(0., -2., 0.).normalize()
+        );
+        let unit = Point3::new(3., 0., 4.).normalize().unwrap();
+        assert!((unit.length() - 1.).abs() < 1e-12);
+        assert_eq!(None, Point3::new(0., 0., 0.).normalize());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let a = Point3::new(1., 2., 3.);
+        let b = Point3::new(4., 6., 8.);
+
+        assert_eq!(Point3::new(5., 8., 11.), a + b);
+        assert_eq!(Point3::new(3., 4., 5.), b - a);
+        assert_eq!(Point3::new(-2., -4., -6.), -a * 2.);
+        assert_eq!(Point3::new(2.5, 4., 5.5), a.lerp(b, 0.5));
+    }
+}
@@ -1,33 +1,305 @@
+use crate::affine::Affine2;
 use crate::line::Line;
 use crate::point::Point;
+use crate::predicates::orient2d;
+use std::fmt;
 
+/// Identifies a polygon within a `Scene`. Assigned by `Scene::add_poly`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PolygonId(pub(crate) usize);
+
+/// A reason that a polygon is invalid. `ring` is the index of the ring with the
+/// problem, where 0 is the outer ring and the holes follow in order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PolygonError {
+    /// The ring has fewer than three points.
+    TooFewPoints { ring: usize },
+    /// A point of the ring has a NaN or infinite coordinate.
+    NonFinitePoint { ring: usize, point: Point },
+    /// The ring visits the same point twice in a row, giving an edge of zero length.
+    ZeroLengthEdge { ring: usize, point: Point },
+    /// Every point of the ring lies on one line, so it encloses no area.
+    ZeroArea { ring: usize },
+}
+
+impl fmt::Display for PolygonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolygonError::TooFewPoints { ring } => {
+                write!(f, "ring {} has fewer than three points", ring)
+            }
+            PolygonError::NonFinitePoint { ring, point } => {
+                write!(f, "ring {} has a non-finite point {:?}", ring, point)
+            }
+            PolygonError::ZeroLengthEdge { ring, point } => {
+                write!(f, "ring {} has a zero-length edge at {:?}", ring, point)
+            }
+            PolygonError::ZeroArea { ring } => write!(f, "ring {} encloses no area", ring),
+        }
+    }
+}
+
+impl std::error::Error for PolygonError {}
+
+/// Rule for deciding which regions are inside a polygon whose rings overlap
+/// or intersect themselves, from the winding number of the rings around a point.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FillRule {
+    /// A point is inside if a ray from it crosses the rings an odd number of times.
+    #[default]
+    EvenOdd,
+    /// A point is inside if the rings wind around it a nonzero number of times.
+    /// Holes must wind in the opposite direction to the ring they are cut from.
+    NonZero,
+}
+
+impl FillRule {
+    /// Returns true if a point with the given winding number is inside.
+    pub fn is_inside(self, winding: i32) -> bool {
+        match self {
+            FillRule::EvenOdd => winding % 2 != 0,
+            FillRule::NonZero => winding != 0,
+        }
+    }
+}
+
+/// A polygon made up of an outer ring and any number of inner rings, which
+/// cut holes out of it. Which regions are inside is decided by the polygon's
+/// fill rule.
+///
+/// The depth of the polygon varies linearly over the plane, as it does for the
+/// image of a planar face, and is given by `z_at`. Where polygons overlap, the
+/// one with the greater depth is in front.
 #[derive(Debug)]
 pub struct Polygon {
+    /// The outer ring.
     pub points: Vec<Point>,
+    /// The inner rings.
+    pub holes: Vec<Vec<Point>>,
+    /// The depth at the origin.
     pub z: f64,
+    /// The rate at which the depth increases along each axis. Zero, for a
+    /// polygon at the same depth everywhere, unless set otherwise.
+    pub z_gradient: Point,
+    pub fill_rule: FillRule,
+    /// The edges of every ring.
     pub lines: Vec<Line>,
 }
 
-impl<'a> Polygon {
+impl Polygon {
     pub fn new(points: Vec<Point>, z: f64) -> Polygon {
+        Polygon::with_holes(points, Vec::new(), z)
+    }
+
+    pub fn with_holes(points: Vec<Point>, holes: Vec<Vec<Point>>, z: f64) -> Polygon {
         let mut poly = Polygon {
             points,
+            holes,
             z,
+            z_gradient: Point::new(0., 0.),
+            fill_rule: FillRule::default(),
             lines: Vec::new(),
         };
+        poly.build_lines();
+        poly
+    }
+
+    /// Construct a polygon from a single ring, or return an error if it is invalid.
+    pub fn try_new(points: Vec<Point>, z: f64) -> Result<Polygon, PolygonError> {
+        Polygon::try_with_holes(points, Vec::new(), z)
+    }
+
+    /// Construct a polygon with holes, or return an error if any ring is invalid.
+    pub fn try_with_holes(
+        points: Vec<Point>,
+        holes: Vec<Vec<Point>>,
+        z: f64,
+    ) -> Result<Polygon, PolygonError> {
+        let poly = Polygon::with_holes(points, holes, z);
+        poly.validate()?;
+        Ok(poly)
+    }
 
-        let mut last_point = poly
-            .points
-            .last()
-            .expect("Tried to build lines from empty polygon.");
+    /// Check that every ring has at least three points, all of them finite, with
+    /// no zero-length edges, and encloses some area. Runs of collinear points
+    /// are allowed; `normalize` removes them.
+    pub fn validate(&self) -> Result<(), PolygonError> {
+        for (ring, points) in self.rings().enumerate() {
+            if let Some(&point) = points.iter().find(|p| !p.is_finite()) {
+                return Err(PolygonError::NonFinitePoint { ring, point });
+            }
+            if points.len() < 3 {
+                return Err(PolygonError::TooFewPoints { ring });
+            }
 
-        for point in poly.points.iter() {
-            poly.lines
-                .push(Line::new_with_poly(*last_point, *point, &poly));
-            last_point = point;
+            let mut last_point = points[points.len() - 1];
+            for &point in points {
+                if point == last_point {
+                    return Err(PolygonError::ZeroLengthEdge { ring, point });
+                }
+                last_point = point;
+            }
+
+            if points
+                .iter()
+                .all(|&point| orient2d(points[0], points[1], point) == 0.)
+            {
+                return Err(PolygonError::ZeroArea { ring });
+            }
         }
 
-        poly
+        Ok(())
+    }
+
+    /// Remove repeated points, which give zero-length edges, and the middle points
+    /// of collinear runs from every ring. Holes left with fewer than three points
+    /// are removed; the outer ring is kept so that `validate` reports it.
+    pub fn normalize(&mut self) {
+        normalize_ring(&mut self.points);
+        for hole in &mut self.holes {
+            normalize_ring(hole);
+        }
+        self.holes.retain(|hole| hole.len() >= 3);
+        self.build_lines();
+    }
+
+    /// Returns the polygon with the given fill rule.
+    pub fn with_fill_rule(mut self, fill_rule: FillRule) -> Polygon {
+        self.fill_rule = fill_rule;
+        self
+    }
+
+    /// Apply `transform` to every ring of the polygon. The lines are transformed
+    /// along with the rings, keeping the id of the polygon they belong to.
+    ///
+    /// A transform which is not invertible collapses the polygon so that it no
+    /// longer passes `validate`. The depth can't follow the points then, so the
+    /// polygon is made flat, at the mean depth of its outer ring's points.
+    pub fn transform(&mut self, transform: &Affine2) {
+        let depths: Vec<f64> = self.points.iter().map(|&p| self.z_at(p)).collect();
+        for ring in std::iter::once(&mut self.points).chain(self.holes.iter_mut()) {
+            for point in ring.iter_mut() {
+                *point = transform.apply(*point);
+            }
+        }
+        for line in &mut self.lines {
+            *line = line.transform(transform);
+        }
+
+        // The depth at a point is the depth at the point it was moved from.
+        if let Some(inverse) = transform.inverse() {
+            let g = self.z_gradient;
+            self.z += g.x * inverse.e + g.y * inverse.f;
+            self.z_gradient = Point::new(
+                g.x * inverse.a + g.y * inverse.b,
+                g.x * inverse.c + g.y * inverse.d,
+            );
+        } else {
+            if !depths.is_empty() {
+                self.z = depths.iter().sum::<f64>() / depths.len() as f64;
+            }
+            self.z_gradient = Point::new(0., 0.);
+        }
+    }
+
+    /// Returns the polygon with the given depth gradient. `z` stays the depth at
+    /// the origin.
+    pub fn with_z_gradient(mut self, z_gradient: Point) -> Polygon {
+        self.z_gradient = z_gradient;
+        self
+    }
+
+    /// Returns the polygon with its depth set from the depths of the points of
+    /// its outer ring, given in the same order. Depths which don't lie on a
+    /// plane are fitted by least squares; if the points are collinear, the
+    /// polygon is given their mean depth everywhere.
+    pub fn with_vertex_depths(mut self, depths: &[f64]) -> Polygon {
+        let (z, z_gradient) = fit_depth_plane(&self.points, depths);
+        self.z = z;
+        self.z_gradient = z_gradient;
+        self
+    }
+
+    /// Returns the depth of the polygon at `point`.
+    pub fn z_at(&self, point: Point) -> f64 {
+        self.z + self.z_gradient.dot(point)
+    }
+
+    /// Returns the rings of the polygon, starting with the outer ring.
+    pub fn rings(&self) -> impl Iterator<Item = &Vec<Point>> {
+        std::iter::once(&self.points).chain(self.holes.iter())
+    }
+
+    /// Replace the lines with the edges of the current rings.
+    fn build_lines(&mut self) {
+        let mut lines = Vec::new();
+        for ring in self.rings() {
+            if let Some(mut last_point) = ring.last() {
+                for point in ring.iter() {
+                    lines.push(Line::new(*last_point, *point));
+                    last_point = point;
+                }
+            }
+        }
+        self.lines = lines;
+    }
+}
+
+/// Returns the depth at the origin and the gradient of the plane which best
+/// fits the given depths at the given points.
+fn fit_depth_plane(points: &[Point], depths: &[f64]) -> (f64, Point) {
+    let n = points.len().min(depths.len());
+    if n == 0 {
+        return (0., Point::new(0., 0.));
+    }
+    let pairs = || points.iter().zip(depths).take(n);
+
+    // Solve the normal equations about the centroid.
+    let centre = pairs().fold(Point::new(0., 0.), |sum, (&p, _)| sum + p) * (1. / n as f64);
+    let mean_z = pairs().map(|(_, &z)| z).sum::<f64>() / n as f64;
+    let (mut sxx, mut sxy, mut syy, mut sxz, mut syz) = (0., 0., 0., 0., 0.);
+    for (&p, &z) in pairs() {
+        let d = p - centre;
+        let dz = z - mean_z;
+        sxx += d.x * d.x;
+        sxy += d.x * d.y;
+        syy += d.y * d.y;
+        sxz += d.x * dz;
+        syz += d.y * dz;
+    }
+
+    let det = sxx * syy - sxy * sxy;
+    let gradient = if det > 0. && det.is_finite() {
+        Point::new((sxz * syy - syz * sxy) / det, (syz * sxx - sxz * sxy) / det)
+    } else {
+        Point::new(0., 0.)
+    };
+    (mean_z - gradient.dot(centre), gradient)
+}
+
+/// Remove repeated points and the middle points of collinear runs from a ring.
+fn normalize_ring(ring: &mut Vec<Point>) {
+    ring.dedup();
+    while ring.len() > 1 && ring.first() == ring.last() {
+        ring.pop();
+    }
+
+    // Removing a point can make its neighbours collinear with the next point,
+    // so repeat until nothing changes.
+    let mut changed = true;
+    while changed && ring.len() >= 3 {
+        changed = false;
+        let mut i = 0;
+        while i < ring.len() && ring.len() >= 3 {
+            let prev = ring[(i + ring.len() - 1) % ring.len()];
+            let next = ring[(i + 1) % ring.len()];
+            if orient2d(prev, ring[i], next) == 0. {
+                ring.remove(i);
+                changed = true;
+            } else {
+                i += 1;
+            }
+        }
     }
 }
 
@@ -62,4 +334,171 @@ mod tests {
         assert_eq!(p3, lines[3].end);
         assert_eq!(LineOrientation::RightToLeft, lines[3].orientation);
     }
+
+    #[test]
+    fn test_hole_lines() {
+        let outer = vec![
+            Point::new(0., 0.),
+            Point::new(10., 0.),
+            Point::new(10., 10.),
+            Point::new(0., 10.),
+        ];
+        let hole = vec![Point::new(3., 3.), Point::new(7., 3.), Point::new(5., 7.)];
+
+        let poly = Polygon::with_holes(outer, vec![hole.clone()], 1.);
+
+        assert_eq!(2, poly.rings().count());
+        assert_eq!(7, poly.lines.len());
+        assert_eq!(Line::new(hole[2], hole[0]), poly.lines[4]);
+        assert_eq!(Line::new(hole[0], hole[1]), poly.lines[5]);
+        assert_eq!(Line::new(hole[1], hole[2]), poly.lines[6]);
+    }
+
+    #[test]
+    fn test_fill_rule() {
+        assert!(!FillRule::EvenOdd.is_inside(0));
+        assert!(FillRule::EvenOdd.is_inside(-1));
+        assert!(!FillRule::EvenOdd.is_inside(2));
+        assert!(!FillRule::NonZero.is_inside(0));
+        assert!(FillRule::NonZero.is_inside(-1));
+        assert!(FillRule::NonZero.is_inside(2));
+    }
+
+    fn square() -> Vec<Point> {
+        vec![
+            Point::new(0., 0.),
+            Point::new(10., 0.),
+            Point::new(10., 10.),
+            Point::new(0., 10.),
+        ]
+    }
+
+    #[test]
+    fn test_try_new() {
+        assert!(Polygon::try_new(square(), 1.).is_ok());
+
+        assert_eq!(
+            PolygonError::TooFewPoints { ring: 0 },
+            Polygon::try_new(Vec::new(), 1.).unwrap_err()
+        );
+        assert_eq!(
+            PolygonError::TooFewPoints { ring: 0 },
+            Polygon::try_new(square()[..2].to_vec(), 1.).unwrap_err()
+        );
+
+        let mut points = square();
+        points[2] = Point::new(f64::NAN, 10.);
+        assert!(matches!(
+            Polygon::try_new(points, 1.),
+            Err(PolygonError::NonFinitePoint { ring: 0, .. })
+        ));
+
+        let mut points = square();
+        points.insert(1, Point::new(0., 0.));
+        assert_eq!(
+            PolygonError::ZeroLengthEdge {
+                ring: 0,
+                point: Point::new(0., 0.)
+            },
+            Polygon::try_new(points, 1.).unwrap_err()
+        );
+
+        let points = vec![Point::new(0., 0.), Point::new(1., 1.), Point::new(3., 3.)];
+        assert_eq!(
+            PolygonError::ZeroArea { ring: 0 },
+            Polygon::try_new(points, 1.).unwrap_err()
+        );
+
+        let hole = vec![Point::new(1., 1.), Point::new(2., 2.)];
+        assert_eq!(
+            PolygonError::TooFewPoints { ring: 1 },
+            Polygon::try_with_holes(square(), vec![hole], 1.).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_normalize() {
+        let points = vec![
+            Point::new(0., 0.),
+            Point::new(5., 0.),
+            Point::new(10., 0.),
+            Point::new(10., 0.),
+            Point::new(10., 10.),
+            Point::new(0., 10.),
+            Point::new(0., 5.),
+            Point::new(0., 0.),
+        ];
+        let hole = vec![Point::new(1., 1.), Point::new(2., 2.), Point::new(3., 3.)];
+        let mut poly = Polygon::with_holes(points, vec![hole], 1.);
+        assert!(poly.validate().is_err());
+
+        poly.normalize();
+
+        assert_eq!(square(), poly.points);
+        assert!(poly.holes.is_empty());
+        assert_eq!(4, poly.lines.len());
+        assert!(poly.validate().is_ok());
+    }
+
+    #[test]
+    fn test_transform() {
+        let hole = vec![Point::new(3., 3.), Point::new(7., 3.), Point::new(5., 7.)];
+        let transform = Affine2::scale(-1., 1.)
+            .then(&Affine2::rotate(0.5))
+            .then(&Affine2::translate(2., -3.));
+
+        let mut poly = Polygon::with_holes(square(), vec![hole.clone()], 1.);
+        for line in &mut poly.lines {
+            line.polygon = Some(PolygonId(4));
+        }
+        poly.transform(&transform);
+
+        let transformed = |ring: Vec<Point>| -> Vec<Point> {
+            ring.into_iter().map(|p| transform.apply(p)).collect()
+        };
+        let expected = Polygon::with_holes(transformed(square()), vec![transformed(hole)], 1.);
+
+        assert_eq!(expected.points, poly.points);
+        assert_eq!(expected.holes, poly.holes);
+        for (expected, line) in expected.lines.iter().zip(&poly.lines) {
+            assert_eq!(expected.start, line.start);
+            assert_eq!(expected.end, line.end);
+            assert_eq!(expected.orientation, line.orientation);
+            assert_eq!(Some(PolygonId(4)), line.polygon);
+        }
+    }
+
+    #[test]
+    fn test_vertex_depths() {
+        let poly = Polygon::new(square(), 0.).with_vertex_depths(&[1., 3., 5., 3.]);
+        assert!((poly.z - 1.).abs() < 1e-12);
+        assert!(poly.z_gradient.approx_eq(Point::new(0.2, 0.2), 1e-12));
+        assert!((poly.z_at(Point::new(5., 0.)) - 2.).abs() < 1e-12);
+
+        // Collinear points give a constant depth.
+        let points = vec![Point::new(0., 0.), Point::new(1., 1.), Point::new(2., 2.)];
+        let poly = Polygon::new(points, 0.).with_vertex_depths(&[1., 2., 6.]);
+        assert_eq!(3., poly.z);
+        assert_eq!(Point::new(0., 0.), poly.z_gradient);
+    }
+
+    #[test]
+    fn test_transform_depth() {
+        let mut poly = Polygon::new(square(), 1.).with_z_gradient(Point::new(0.5, -1.));
+        let point = Point::new(3., 7.);
+        let z = poly.z_at(point);
+
+        let transform = Affine2::rotate(0.3)
+            .then(&Affine2::scale(-2., 1.))
+            .then(&Affine2::translate(4., 5.));
+        poly.transform(&transform);
+
+        assert!((poly.z_at(transform.apply(point)) - z).abs() < 1e-12);
+
+        // Collapsing the polygon onto a line leaves it flat at its mean depth.
+        let mut poly = Polygon::new(square(), 0.).with_vertex_depths(&[1., 3., 5., 3.]);
+        poly.transform(&Affine2::scale(1., 0.));
+        assert!((poly.z - 3.).abs() < 1e-12);
+        assert_eq!(Point::new(0., 0.), poly.z_gradient);
+    }
 }
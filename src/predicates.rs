@@ -0,0 +1,160 @@
+//! Adaptive-precision geometric predicates, after Shewchuk's "Adaptive
+//! Precision Floating-Point Arithmetic and Fast Robust Geometric Predicates".
+//!
+//! Each predicate first evaluates its determinant in ordinary floating point
+//! along with a bound on the rounding error. Only if the result is too close
+//! to zero for its sign to be trusted is it recomputed exactly, by summing the
+//! exact products of the input coordinates as a floating-point expansion.
+
+use crate::point::Point;
+
+/// Half the distance between 1.0 and the next representable value, i.e. the
+/// relative rounding error of a single floating point operation.
+const EPSILON: f64 = f64::EPSILON / 2.;
+
+/// Relative error bound for the floating point evaluation of `cross`.
+const CROSS_ERROR_BOUND: f64 = (3. + 16. * EPSILON) * EPSILON;
+
+/// Returns `(x, y)` such that `x` is the floating point sum of `a` and `b`
+/// and `x + y == a + b` exactly.
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let x = a + b;
+    let b_virtual = x - a;
+    let a_virtual = x - b_virtual;
+    let b_roundoff = b - b_virtual;
+    let a_roundoff = a - a_virtual;
+    (x, a_roundoff + b_roundoff)
+}
+
+/// Returns `(x, y)` such that `x` is the floating point product of `a` and
+/// `b` and `x + y == a * b` exactly.
+fn two_product(a: f64, b: f64) -> (f64, f64) {
+    let x = a * b;
+    (x, a.mul_add(b, -x))
+}
+
+/// Adds `b` to the expansion `e`, whose components are nonoverlapping and
+/// ordered by increasing magnitude. Zero components are dropped, so an
+/// expansion equal to zero is empty.
+fn grow_expansion(e: &mut Vec<f64>, b: f64) {
+    let mut q = b;
+    let mut h = Vec::with_capacity(e.len() + 1);
+    for &component in e.iter() {
+        let (sum, error) = two_sum(q, component);
+        q = sum;
+        if error != 0. {
+            h.push(error);
+        }
+    }
+    if q != 0. {
+        h.push(q);
+    }
+    *e = h;
+}
+
+/// Returns the most significant component of the exact sum of the products of
+/// each pair, which has the same sign as the sum.
+fn sum_of_products(terms: &[(f64, f64)]) -> f64 {
+    let mut e = Vec::new();
+    for &(a, b) in terms {
+        let (product, error) = two_product(a, b);
+        grow_expansion(&mut e, error);
+        grow_expansion(&mut e, product);
+    }
+    e.last().copied().unwrap_or(0.)
+}
+
+/// Returns the cross product of the vectors `b - a` and `d - c`. The sign of
+/// the result is exact: it is positive if `d - c` points counterclockwise of
+/// `b - a`, negative if it points clockwise, and zero if they are parallel.
+pub fn cross(a: Point, b: Point, c: Point, d: Point) -> f64 {
+    let left = (b.x - a.x) * (d.y - c.y);
+    let right = (b.y - a.y) * (d.x - c.x);
+    let det = left - right;
+
+    let error_bound = CROSS_ERROR_BOUND * (left.abs() + right.abs());
+    if det > error_bound || -det > error_bound {
+        return det;
+    }
+
+    sum_of_products(&[
+        (b.x, d.y),
+        (-b.x, c.y),
+        (-a.x, d.y),
+        (a.x, c.y),
+        (-b.y, d.x),
+        (b.y, c.x),
+        (a.y, d.x),
+        (-a.y, c.x),
+    ])
+}
+
+/// Returns a value which is positive if `c` lies to the left of the directed
+/// line from `a` to `b` (i.e. `a`, `b`, `c` are in counterclockwise order),
+/// negative if it lies to the right, and zero if the points are collinear.
+/// The sign of the result is exact; its magnitude approximates twice the area
+/// of the triangle.
+pub fn orient2d(a: Point, b: Point, c: Point) -> f64 {
+    cross(a, b, a, c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_orient2d() {
+        let a = Point::new(0., 0.);
+        let b = Point::new(10., 0.);
+
+        assert!(orient2d(a, b, Point::new(5., 1.)) > 0.);
+        assert!(orient2d(a, b, Point::new(5., -1.)) < 0.);
+        assert_eq!(0., orient2d(a, b, Point::new(20., 0.)));
+    }
+
+    #[test]
+    fn test_cross() {
+        let a = Point::new(0., 0.);
+        let b = Point::new(1., 1.);
+
+        assert!(cross(a, b, a, Point::new(1., 2.)) > 0.);
+        assert!(cross(a, b, a, Point::new(2., 1.)) < 0.);
+        assert_eq!(0., cross(a, b, Point::new(5., 3.), Point::new(7., 5.)));
+    }
+
+    #[test]
+    fn test_orient2d_near_degenerate() {
+        // Points on a fine grid near a line, as in Kettner et al.'s
+        // "Classroom Examples of Robustness Problems in Geometric Computations".
+        // Coordinates are multiples of 2^-53, so scaling by 2^53 makes them
+        // integers whose determinant can be computed exactly.
+        let ulp = 2f64.powi(-53);
+        let scale = 2f64.powi(53);
+        let q = Point::new(12., 12.);
+        let r = Point::new(24., 24.);
+
+        for i in 0..64 {
+            for j in 0..64 {
+                let p = Point::new(0.5 + i as f64 * ulp, 0.5 + j as f64 * ulp);
+
+                let [px, py, qx, qy, rx, ry] =
+                    [p.x, p.y, q.x, q.y, r.x, r.y].map(|v| (v * scale) as i128);
+                let exact = ((qx - px) * (ry - py) - (qy - py) * (rx - px)).signum();
+
+                let sign = |v: f64| {
+                    if v > 0. {
+                        1
+                    } else if v < 0. {
+                        -1
+                    } else {
+                        0
+                    }
+                };
+                assert_eq!(exact, sign(orient2d(p, q, r)));
+                assert_eq!(exact, sign(orient2d(q, r, p)));
+                assert_eq!(exact, sign(orient2d(r, p, q)));
+                assert_eq!(-exact, sign(orient2d(q, p, r)));
+            }
+        }
+    }
+}
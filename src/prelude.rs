@@ -1,5 +1,13 @@
+pub use crate::affine::Affine2;
+pub use crate::camera::{Camera, Projection};
 pub use crate::debug_draw::DebugDraw;
+pub use crate::error::Error;
 pub use crate::line::Line;
+pub use crate::mesh::Mesh;
 pub use crate::point::Point;
-pub use crate::polygon::Polygon;
+pub use crate::point3::Point3;
+pub use crate::polygon::{FillRule, Polygon, PolygonError, PolygonId};
 pub use crate::scene::Scene;
+pub use crate::scene3d::{Face, Scene3D};
+pub use crate::svg_export::{SvgExporter, Unit};
+pub use crate::svg_import::SvgImporter;
@@ -1,44 +1,54 @@
-use crate::line::Line;
+use crate::data_structures::RedBlackTree;
+use crate::line::{Line, LineIntersection};
 use crate::point::Point;
 use crate::scene::Scene;
 use crate::vertex::Vertex;
 use std::cmp::Ordering;
-use std::collections::BinaryHeap;
+use std::collections::{BinaryHeap, HashSet};
 
 #[derive(PartialEq, PartialOrd, Ord, Eq)]
 pub enum LineEvent {
     Begin,
     End,
+    Intersect,
 }
 
 /// Represents an entry into the priority queue of events we encounter as we
 /// scan the scene. Events are sorted in order of where in the scene they appear.
-#[derive(PartialEq, Ord, Eq)]
+#[derive(PartialEq, Eq)]
 pub enum SceneEvent<'a> {
     VertexEvent(Vertex<'a>),
-    IntersectionEvent(Point, &'a Line, LineEvent),
+    /// Two lines cross at the given point. The first line is below the second
+    /// one to the left of the point.
+    IntersectionEvent(Point, &'a Line, &'a Line),
 }
 
-impl<'a> PartialOrd for SceneEvent<'a> {
+impl<'a> Ord for SceneEvent<'a> {
     // Ordering is inverted because BinaryHeap is a max queue and we want a
     // min queue.
-    fn partial_cmp(&self, other: &SceneEvent) -> Option<Ordering> {
+    fn cmp(&self, other: &SceneEvent) -> Ordering {
         match other.point().cmp(&self.point()) {
             Ordering::Equal => match self {
                 SceneEvent::VertexEvent(vs) => match other {
-                    SceneEvent::VertexEvent(vo) => vo.partial_cmp(vs),
-                    SceneEvent::IntersectionEvent(_, _, _) => Some(Ordering::Less),
+                    SceneEvent::VertexEvent(vo) => vo.cmp(vs),
+                    SceneEvent::IntersectionEvent(_, _, _) => Ordering::Less,
                 },
-                SceneEvent::IntersectionEvent(_, ls, es) => match other {
-                    SceneEvent::VertexEvent(_) => Some(Ordering::Greater),
-                    SceneEvent::IntersectionEvent(_, lo, eo) => (lo, eo).partial_cmp(&(ls, es)),
+                SceneEvent::IntersectionEvent(_, ls1, ls2) => match other {
+                    SceneEvent::VertexEvent(_) => Ordering::Greater,
+                    SceneEvent::IntersectionEvent(_, lo1, lo2) => (lo1, lo2).cmp(&(ls1, ls2)),
                 },
             },
-            ord => Some(ord),
+            ord => ord,
         }
     }
 }
 
+impl<'a> PartialOrd for SceneEvent<'a> {
+    fn partial_cmp(&self, other: &SceneEvent) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl<'a> SceneEvent<'a> {
     pub fn point(&self) -> Point {
         match &self {
@@ -48,6 +58,87 @@ impl<'a> SceneEvent<'a> {
     }
 }
 
+/// Identifies an unordered pair of lines by address.
+type LinePair = (*const Line, *const Line);
+
+fn line_pair(l1: &Line, l2: &Line) -> LinePair {
+    let (p1, p2) = (l1 as *const Line, l2 as *const Line);
+    if p1 < p2 {
+        (p1, p2)
+    } else {
+        (p2, p1)
+    }
+}
+
+/// The status of the sweep: the lines which cross the sweep line at the cursor,
+/// ordered from bottom to top just to the right of the cursor. Lines are looked
+/// up by address, and their order is only changed by inserting, removing, and
+/// swapping lines, so it stays consistent even where lines are too close
+/// together to tell apart numerically.
+pub struct SweepStatus<'a> {
+    tree: RedBlackTree<'a, Line>,
+}
+
+impl<'a> SweepStatus<'a> {
+    pub fn new() -> SweepStatus<'a> {
+        SweepStatus {
+            tree: RedBlackTree::new(),
+        }
+    }
+
+    /// Insert a line which starts at the cursor.
+    pub fn insert(&mut self, line: &'a Line) {
+        self.tree
+            .insert_by(line, |line, other| other.cmp_at_start(line).reverse());
+    }
+
+    /// Remove a line, returning false if it was not in the status.
+    pub fn remove(&mut self, line: &Line) -> bool {
+        match self.tree.get(line) {
+            Some(node) => {
+                node.delete();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Exchange the positions of two lines.
+    pub fn swap(&mut self, line1: &Line, line2: &Line) {
+        self.tree.swap(line1, line2);
+    }
+
+    /// Returns the line directly below the given line, if any.
+    pub fn below(&mut self, line: &Line) -> Option<&'a Line> {
+        Some(self.tree.get(line)?.prev()?.key())
+    }
+
+    /// Returns the line directly above the given line, if any.
+    pub fn above(&mut self, line: &Line) -> Option<&'a Line> {
+        Some(self.tree.get(line)?.next()?.key())
+    }
+
+    /// Returns an iterator over the lines, from bottom to top.
+    pub fn iter(&self) -> impl Iterator<Item = &'a Line> + '_ {
+        self.tree.iter()
+    }
+
+    /// Returns all of the lines, from bottom to top.
+    pub fn lines(&self) -> Vec<&'a Line> {
+        self.iter().collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+}
+
+impl<'a> Default for SweepStatus<'a> {
+    fn default() -> SweepStatus<'a> {
+        SweepStatus::new()
+    }
+}
+
 /// Represents the state of the scanner at a discrete step in the
 /// scanning process.
 pub struct ScanState<'a> {
@@ -56,6 +147,12 @@ pub struct ScanState<'a> {
     pub cursor: Option<Point>,
     /// A priority queue of known remaining events in the scene.
     pub events: BinaryHeap<SceneEvent<'a>>,
+    /// Lines which cross the sweep line at the cursor. Vertical lines are not
+    /// included.
+    pub active: SweepStatus<'a>,
+    /// Pairs of lines for which an intersection event has been queued. Two
+    /// segments cross at most once, so each pair only needs to be queued once.
+    crossings: HashSet<LinePair>,
 }
 
 type StepResult<'a> = Vec<(&'a Line, LineEvent)>;
@@ -71,6 +168,13 @@ impl<'a> ScanState<'a> {
                     let mut vs: StepResult =
                         Vec::with_capacity(v.start_lines.len() + v.end_lines.len());
 
+                    for &line in &v.end_lines {
+                        self.remove_active(line);
+                    }
+                    for &line in &v.start_lines {
+                        self.insert_active(line);
+                    }
+
                     for &line in &v.start_lines {
                         vs.push((line, LineEvent::Begin));
                     }
@@ -80,7 +184,10 @@ impl<'a> ScanState<'a> {
 
                     vs
                 }
-                SceneEvent::IntersectionEvent(_, line, line_event) => vec![(line, line_event)],
+                SceneEvent::IntersectionEvent(_, lower, upper) => {
+                    self.cross_active(lower, upper);
+                    vec![(lower, LineEvent::Intersect), (upper, LineEvent::Intersect)]
+                }
             }
         } else {
             self.cursor = None;
@@ -92,7 +199,89 @@ impl<'a> ScanState<'a> {
         self.events.is_empty()
     }
 
-    pub fn new(scene: &Scene) -> ScanState {
+    /// Returns the point of the next event in the queue, or `None` if the
+    /// queue is empty.
+    pub fn next_point(&self) -> Option<Point> {
+        self.events.peek().map(|e| e.point())
+    }
+
+    /// Add a line which begins at the cursor to the active lines.
+    fn insert_active(&mut self, line: &'a Line) {
+        if line.is_vertical() {
+            // Vertical lines begin and end at the same x, so they never cross
+            // the sweep line between events.
+            return;
+        }
+
+        self.active.insert(line);
+        if let Some(below) = self.active.below(line) {
+            self.check_crossing(below, line);
+        }
+        if let Some(above) = self.active.above(line) {
+            self.check_crossing(line, above);
+        }
+    }
+
+    /// Remove an ending line from the active lines. Its neighbours become adjacent.
+    fn remove_active(&mut self, line: &Line) {
+        let below = self.active.below(line);
+        let above = self.active.above(line);
+        if self.active.remove(line) {
+            if let (Some(below), Some(above)) = (below, above) {
+                self.check_crossing(below, above);
+            }
+        }
+    }
+
+    /// Swap two adjacent lines which cross at the cursor.
+    fn cross_active(&mut self, lower: &'a Line, upper: &'a Line) {
+        match self.active.above(lower) {
+            Some(above) if std::ptr::eq(above, upper) => {
+                self.active.swap(lower, upper);
+                if let Some(below) = self.active.below(upper) {
+                    self.check_crossing(below, upper);
+                }
+                if let Some(above) = self.active.above(lower) {
+                    self.check_crossing(lower, above);
+                }
+            }
+            _ => {
+                // When several lines cross at (nearly) the same point, the pair
+                // may have been separated by another crossing. Forget it so that
+                // it is queued again if the lines become adjacent.
+                self.crossings.remove(&line_pair(lower, upper));
+            }
+        }
+    }
+
+    /// Queue an intersection event if two adjacent active lines cross to the
+    /// right of the cursor.
+    fn check_crossing(&mut self, lower: &'a Line, upper: &'a Line) {
+        if !lower.rises_above(upper) {
+            return;
+        }
+
+        if let LineIntersection::Point(point) = lower.intersect(upper) {
+            if point.x >= lower.end.x.min(upper.end.x) {
+                // The lines meet where one of them ends, so the vertex event
+                // there removes it instead.
+                return;
+            }
+
+            if self.crossings.insert(line_pair(lower, upper)) {
+                // Rounding can put the intersection slightly behind the cursor,
+                // but the lines are out of order and must be swapped now.
+                let point = match self.cursor {
+                    Some(cursor) if point < cursor => cursor,
+                    _ => point,
+                };
+                self.events
+                    .push(SceneEvent::IntersectionEvent(point, lower, upper));
+            }
+        }
+    }
+
+    pub fn new(scene: &Scene) -> ScanState<'_> {
         let vertices = scene.vertices();
         let mut events = BinaryHeap::with_capacity(vertices.len());
 
@@ -103,6 +292,74 @@ impl<'a> ScanState<'a> {
         ScanState {
             cursor: None,
             events,
+            active: SweepStatus::new(),
+            crossings: HashSet::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polygon::Polygon;
+
+    fn diamond(cx: f64, cy: f64, r: f64, z: f64) -> Polygon {
+        Polygon::new(
+            vec![
+                Point::new(cx - r, cy),
+                Point::new(cx, cy + r),
+                Point::new(cx + r, cy),
+                Point::new(cx, cy - r),
+            ],
+            z,
+        )
+    }
+
+    #[test]
+    fn test_intersection_events() {
+        let mut scene = Scene::new();
+        scene.add_poly(diamond(10., 10., 5., 1.)).unwrap();
+        scene.add_poly(diamond(15., 10., 5., 2.)).unwrap();
+
+        let mut state = ScanState::new(&scene);
+        let mut intersections = Vec::new();
+        while !state.done() {
+            let lines = state.step();
+            if lines.iter().any(|(_, e)| *e == LineEvent::Intersect) {
+                intersections.push(state.cursor.unwrap());
+            }
+        }
+
+        assert_eq!(
+            vec![Point::new(12.5, 7.5), Point::new(12.5, 12.5)],
+            intersections
+        );
+        assert!(state.active.is_empty());
+    }
+
+    #[test]
+    fn test_active_order() {
+        let mut scene = Scene::new();
+        scene.add_poly(diamond(10., 10., 5., 1.)).unwrap();
+        scene.add_poly(diamond(15., 10., 5., 2.)).unwrap();
+
+        let mut state = ScanState::new(&scene);
+        while !state.done() {
+            state.step();
+            if let Some(next) = state.next_point() {
+                let cursor = state.cursor.unwrap();
+                if next.x > cursor.x {
+                    // Active lines are ordered bottom to top across the span.
+                    let mid = (cursor.x + next.x) / 2.;
+                    let ys: Vec<f64> = state
+                        .active
+                        .lines()
+                        .iter()
+                        .map(|l| l.y_at(mid).unwrap())
+                        .collect();
+                    assert!(ys.windows(2).all(|w| w[0] <= w[1]), "{:?}", ys);
+                }
+            }
         }
     }
 }
@@ -1,11 +1,17 @@
+use crate::affine::Affine2;
+use crate::error::Error;
+use crate::line::Line;
 use crate::point::Point;
-use crate::polygon::Polygon;
+use crate::polygon::{Polygon, PolygonId};
+use crate::svg_import::SvgImporter;
 use crate::vertex::Vertex;
+use crate::visibility::VisibilitySweep;
 use std::collections::BTreeMap;
+use std::path::Path;
 
 /// A container that owns multiple polygons.
 pub struct Scene {
-    pub polys: Vec<Polygon>,
+    polys: Vec<Polygon>,
 }
 
 impl<'a> Scene {
@@ -13,8 +19,56 @@ impl<'a> Scene {
         Scene { polys: Vec::new() }
     }
 
-    pub fn add_poly(&mut self, poly: Polygon) {
-        self.polys.push(poly)
+    /// Read the filled shapes of an SVG file into a scene, with later elements
+    /// in front of earlier ones. See `SvgImporter`, which can also set the
+    /// tolerance for flattening curves.
+    pub fn from_svg<P: AsRef<Path>>(path: P) -> Result<Scene, Error> {
+        SvgImporter::new().load(path)
+    }
+
+    /// Add a polygon to the scene, returning the id assigned to it. The
+    /// polygon's lines are marked with the id. Returns an error if the polygon
+    /// is not valid.
+    pub fn add_poly(&mut self, mut poly: Polygon) -> Result<PolygonId, Error> {
+        poly.validate()?;
+
+        let id = PolygonId(self.polys.len());
+        for line in &mut poly.lines {
+            line.polygon = Some(id);
+        }
+        self.polys.push(poly);
+        Ok(id)
+    }
+
+    /// Returns the polygon with the given id, if it belongs to this scene.
+    pub fn polygon(&self, id: PolygonId) -> Option<&Polygon> {
+        self.polys.get(id.0)
+    }
+
+    /// Returns the polygons in the scene, in the order they were added.
+    pub fn polys(&self) -> &[Polygon] {
+        &self.polys
+    }
+
+    /// Apply `transform` to every polygon in the scene. Polygon ids are kept.
+    /// Returns an error, leaving the scene unchanged, if any transformed
+    /// polygon is not valid, as when the transform has a non-finite
+    /// coefficient or collapses the plane.
+    pub fn transform(&mut self, transform: &Affine2) -> Result<(), Error> {
+        // Check every transformed polygon before changing any of them.
+        let apply = |ring: &Vec<Point>| ring.iter().map(|&p| transform.apply(p)).collect();
+        for poly in &self.polys {
+            Polygon::try_with_holes(
+                apply(&poly.points),
+                poly.holes.iter().map(apply).collect(),
+                0.,
+            )?;
+        }
+
+        for poly in &mut self.polys {
+            poly.transform(transform);
+        }
+        Ok(())
     }
 
     /// Return vertices associated with the polygons in this scene
@@ -29,18 +83,27 @@ impl<'a> Scene {
                     .entry(line.start)
                     .or_insert_with(|| Vertex::new(line.start))
                     .start_lines
-                    .insert(line);
+                    .push(line);
 
                 // Add vertex for end point.
                 vertices
                     .entry(line.end)
                     .or_insert_with(|| Vertex::new(line.end))
                     .end_lines
-                    .insert(line);
+                    .push(line);
             }
         }
 
-        vertices.into_iter().map(|(_, v)| v).collect()
+        vertices.into_values().collect()
+    }
+
+    /// Return the segments of each polygon's lines which are not hidden
+    /// behind another polygon. Which polygon is in front is decided at each
+    /// point from the polygons' depths there, so polygons which pass through
+    /// each other, or which overlap cyclically, are drawn correctly. Each
+    /// segment is marked with the id of the polygon it is an edge of.
+    pub fn visible_lines(&self) -> Vec<Line> {
+        VisibilitySweep::new(self).run()
     }
 }
 
@@ -53,6 +116,7 @@ impl Default for Scene {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::polygon::PolygonError;
 
     #[test]
     fn test_vertices() {
@@ -63,7 +127,7 @@ mod tests {
 
         let poly = Polygon::new(vec![p1, p2, p3, p4], 1.);
         let mut scene = Scene::new();
-        scene.add_poly(poly);
+        scene.add_poly(poly).unwrap();
         let verts = scene.vertices();
 
         assert_eq!(p1, verts[0].point);
@@ -79,4 +143,115 @@ mod tests {
         assert_eq!(0, verts[3].start_lines.len());
         assert_eq!(2, verts[3].end_lines.len());
     }
+
+    #[test]
+    fn test_polygon_ids() {
+        let mut scene = Scene::new();
+        let id1 = scene
+            .add_poly(Polygon::new(
+                vec![Point::new(0., 0.), Point::new(1., 1.), Point::new(2., 0.)],
+                1.,
+            ))
+            .unwrap();
+        let id2 = scene
+            .add_poly(Polygon::new(
+                vec![Point::new(0., 0.), Point::new(1., 1.), Point::new(2., 0.)],
+                2.,
+            ))
+            .unwrap();
+
+        assert_ne!(id1, id2);
+        assert_eq!(1., scene.polygon(id1).unwrap().z);
+        assert_eq!(2., scene.polygon(id2).unwrap().z);
+        for id in &[id1, id2] {
+            let poly = scene.polygon(*id).unwrap();
+            assert!(poly.lines.iter().all(|line| line.polygon == Some(*id)));
+        }
+
+        // Identical lines of different polygons are distinct.
+        assert_ne!(
+            scene.polygon(id1).unwrap().lines[0],
+            scene.polygon(id2).unwrap().lines[0]
+        );
+    }
+
+    #[test]
+    fn test_add_invalid_poly() {
+        let mut scene = Scene::new();
+        let poly = Polygon::new(vec![Point::new(0., 0.), Point::new(1., f64::NAN)], 1.);
+
+        assert!(matches!(
+            scene.add_poly(poly),
+            Err(Error::InvalidPolygon(PolygonError::NonFinitePoint {
+                ring: 0,
+                ..
+            }))
+        ));
+        assert!(scene.polys().is_empty());
+    }
+
+    #[test]
+    fn test_transform() {
+        let mut scene = Scene::new();
+        let id = scene
+            .add_poly(Polygon::new(
+                vec![
+                    Point::new(0., 0.),
+                    Point::new(4., 0.),
+                    Point::new(4., 4.),
+                    Point::new(0., 4.),
+                ],
+                1.,
+            ))
+            .unwrap();
+        scene
+            .add_poly(Polygon::new(
+                vec![Point::new(2., 1.), Point::new(6., 2.), Point::new(3., 5.)],
+                2.,
+            ))
+            .unwrap();
+
+        // A reflection and a translation are exact, so the visible lines of the
+        // transformed scene are exactly the transformed visible lines.
+        let transform = Affine2::scale(-1., 1.).then(&Affine2::translate(10., 3.));
+        let mut expected: Vec<Line> = scene
+            .visible_lines()
+            .iter()
+            .map(|line| line.transform(&transform))
+            .collect();
+        expected.sort();
+
+        scene.transform(&transform).unwrap();
+        let mut visible = scene.visible_lines();
+        visible.sort();
+
+        assert_eq!(expected, visible);
+        let poly = scene.polygon(id).unwrap();
+        assert_eq!(Point::new(10., 3.), poly.points[0]);
+        assert!(poly.lines.iter().all(|line| line.polygon == Some(id)));
+    }
+
+    #[test]
+    fn test_invalid_transform() {
+        let mut scene = Scene::new();
+        let square = vec![
+            Point::new(0., 0.),
+            Point::new(4., 0.),
+            Point::new(4., 4.),
+            Point::new(0., 4.),
+        ];
+        scene.add_poly(Polygon::new(square.clone(), 1.)).unwrap();
+
+        assert!(matches!(
+            scene.transform(&Affine2::scale(f64::NAN, 1.)),
+            Err(Error::InvalidPolygon(PolygonError::NonFinitePoint { .. }))
+        ));
+        assert!(matches!(
+            scene.transform(&Affine2::scale(1e300, 1e300).then(&Affine2::scale(1e300, 1.))),
+            Err(Error::InvalidPolygon(PolygonError::NonFinitePoint { .. }))
+        ));
+        assert!(scene.transform(&Affine2::scale(0., 1.)).is_err());
+        assert_eq!(square, scene.polys()[0].points);
+        assert_eq!(4, scene.visible_lines().len());
+    }
 }
@@ -0,0 +1,402 @@
+use crate::camera::Camera;
+use crate::error::Error;
+use crate::point3::Point3;
+use crate::polygon::{Polygon, PolygonError};
+use crate::scene::Scene;
+
+/// A planar polygon in space.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Face {
+    pub points: Vec<Point3>,
+}
+
+impl Face {
+    pub fn new(points: Vec<Point3>) -> Face {
+        Face { points }
+    }
+}
+
+/// A container of faces in space, which can be viewed through a `Camera` to
+/// give a 2D `Scene`.
+#[derive(Clone, Debug, Default)]
+pub struct Scene3D {
+    faces: Vec<Face>,
+}
+
+impl Scene3D {
+    pub fn new() -> Scene3D {
+        Scene3D { faces: Vec::new() }
+    }
+
+    pub fn add_face(&mut self, face: Face) {
+        self.faces.push(face);
+    }
+
+    /// Add a mesh of faces, each given as a list of indices into `vertices`.
+    /// Returns an error, without adding any faces, if an index is out of range.
+    pub fn add_mesh<F: AsRef<[usize]>>(
+        &mut self,
+        vertices: &[Point3],
+        faces: &[F],
+    ) -> Result<(), Error> {
+        let faces = faces
+            .iter()
+            .map(|face| {
+                face.as_ref()
+                    .iter()
+                    .map(|&index| {
+                        vertices
+                            .get(index)
+                            .copied()
+                            .ok_or(Error::VertexIndexOutOfRange {
+                                index,
+                                len: vertices.len(),
+                            })
+                    })
+                    .collect::<Result<Vec<_>, Error>>()
+                    .map(Face::new)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        self.faces.extend(faces);
+        Ok(())
+    }
+
+    /// Returns the faces in the scene, in the order they were added.
+    pub fn faces(&self) -> &[Face] {
+        &self.faces
+    }
+
+    /// Project the faces through `camera` into a 2D scene, in image coordinates.
+    /// Faces are clipped to the camera's near plane. Faces which are seen edge
+    /// on, or which are entirely clipped away, enclose no area in the image and
+    /// are left out. Each polygon's depth varies across it as the depth of the
+    /// face does, so faces which pass through each other hide each other's
+    /// edges correctly.
+    ///
+    /// Returns an error if a face has a point with a NaN or infinite coordinate.
+    pub fn project(&self, camera: &Camera) -> Result<Scene, Error> {
+        let mut scene = Scene::new();
+        for face in &self.faces {
+            let view: Vec<Point3> = face.points.iter().map(|&p| camera.to_view(p)).collect();
+            let clipped = camera.clip_near(&view);
+            if clipped.is_empty() {
+                continue;
+            }
+
+            let (points, depths): (Vec<_>, Vec<_>) =
+                clipped.iter().map(|&p| camera.project_view(p)).unzip();
+            let mut poly = Polygon::new(points, 0.).with_vertex_depths(&depths);
+            poly.normalize();
+            match poly.validate() {
+                Ok(()) => {
+                    scene.add_poly(poly)?;
+                }
+                Err(PolygonError::NonFinitePoint { point, .. }) => {
+                    return Err(Error::NonFinitePoint(point))
+                }
+                Err(_) => (),
+            }
+        }
+        Ok(scene)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Projection;
+    use crate::line::Line;
+    use crate::point::Point;
+
+    /// The corners of a cube with the given centre and side length. Bit 0 of
+    /// the index selects the x side, bit 1 the y side and bit 2 the z side.
+    fn cube_vertices(centre: Point3, side: f64) -> Vec<Point3> {
+        (0..8)
+            .map(|i| {
+                let offset = |bit: usize| if i & bit == 0 { -0.5 } else { 0.5 } * side;
+                centre + Point3::new(offset(1), offset(2), offset(4))
+            })
+            .collect()
+    }
+
+    const CUBE_FACES: [[usize; 4]; 6] = [
+        [0, 2, 3, 1],
+        [4, 5, 7, 6],
+        [0, 1, 5, 4],
+        [2, 6, 7, 3],
+        [0, 4, 6, 2],
+        [1, 3, 7, 5],
+    ];
+
+    fn camera(projection: Projection) -> Camera {
+        Camera::look_at(
+            Point3::new(0., 0., 5.),
+            Point3::new(0., 0., 0.),
+            Point3::new(0., 1., 0.),
+            projection,
+        )
+        .unwrap()
+    }
+
+    fn square_lines(r: f64) -> Vec<Line> {
+        let mut lines = vec![
+            Line::new(Point::new(-r, -r), Point::new(r, -r)),
+            Line::new(Point::new(r, -r), Point::new(r, r)),
+            Line::new(Point::new(-r, r), Point::new(r, r)),
+            Line::new(Point::new(-r, -r), Point::new(-r, r)),
+        ];
+        lines.sort();
+        lines
+    }
+
+    /// Returns the visible lines, sorted and without the ids of their polygons.
+    fn visible_sorted(scene: &Scene) -> Vec<Line> {
+        let mut lines: Vec<Line> = scene
+            .visible_lines()
+            .iter()
+            .map(|line| Line::new(line.start, line.end))
+            .collect();
+        lines.sort();
+        lines
+    }
+
+    #[test]
+    fn test_add_mesh() {
+        let mut scene = Scene3D::new();
+        let vertices = cube_vertices(Point3::new(0., 0., 0.), 2.);
+        scene.add_mesh(&vertices, &CUBE_FACES).unwrap();
+        assert_eq!(6, scene.faces().len());
+        assert_eq!(vertices[7], scene.faces()[1].points[2]);
+
+        assert!(matches!(
+            scene.add_mesh(&vertices, &[vec![0, 1, 8]]),
+            Err(Error::VertexIndexOutOfRange { index: 8, len: 8 })
+        ));
+        assert_eq!(6, scene.faces().len());
+    }
+
+    #[test]
+    fn test_orthographic_cube() {
+        // Seen face on, only the outline of the front face is visible, and the
+        // side faces are seen edge on.
+        let mut scene = Scene3D::new();
+        let vertices = cube_vertices(Point3::new(0., 0., 0.), 2.);
+        scene.add_mesh(&vertices, &CUBE_FACES).unwrap();
+
+        let projected = scene
+            .project(&camera(Projection::Orthographic { height: 4. }))
+            .unwrap();
+        assert_eq!(2, projected.polys().len());
+        assert_eq!(square_lines(0.5), visible_sorted(&projected));
+    }
+
+    #[test]
+    fn test_perspective_cube() {
+        let mut scene = Scene3D::new();
+        let vertices = cube_vertices(Point3::new(0., 0., 0.), 2.);
+        scene.add_mesh(&vertices, &CUBE_FACES).unwrap();
+
+        let fov_y = 2. * 0.25f64.atan();
+        let projected = scene
+            .project(&camera(Projection::Perspective { fov_y }))
+            .unwrap();
+        assert_eq!(6, projected.polys().len());
+
+        // The front face is 4 units away, so its corners project to (±1, ±1)
+        // up to rounding, and it hides the rest of the cube.
+        let visible = projected.visible_lines();
+        assert_eq!(4, visible.len());
+        for (line, expected) in visible_sorted(&projected).iter().zip(square_lines(1.)) {
+            assert!(line.start.approx_eq(expected.start, 1e-12));
+            assert!(line.end.approx_eq(expected.end, 1e-12));
+        }
+    }
+
+    /// Assert that two sets of lines are the same, up to rounding.
+    fn assert_lines_approx_eq(mut expected: Vec<Line>, mut lines: Vec<Line>) {
+        expected.sort();
+        lines.sort();
+        assert_eq!(expected.len(), lines.len(), "{:?}", lines);
+        for (expected, line) in expected.iter().zip(&lines) {
+            assert!(
+                line.start.approx_eq(expected.start, 1e-9)
+                    && line.end.approx_eq(expected.end, 1e-9),
+                "Expected {:?}, got {:?}",
+                expected,
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn test_sloped_face() {
+        // A tall face which leans back steeply towards the bottom, and a small
+        // square in front of its lower part. The square is further away than
+        // the tall face on average, but nearer where they overlap.
+        let mut scene = Scene3D::new();
+        scene.add_face(Face::new(vec![
+            Point3::new(-1., -1.5, -3.),
+            Point3::new(1., -1.5, -3.),
+            Point3::new(1., 1.5, 1.),
+            Point3::new(-1., 1.5, 1.),
+        ]));
+        scene.add_face(Face::new(vec![
+            Point3::new(-0.25, -1.25, -1.8),
+            Point3::new(0.25, -1.25, -1.8),
+            Point3::new(0.25, -0.75, -1.8),
+            Point3::new(-0.25, -0.75, -1.8),
+        ]));
+
+        let projected = scene
+            .project(&camera(Projection::Orthographic { height: 4. }))
+            .unwrap();
+        let rect = |x: f64, y0: f64, y1: f64| {
+            vec![
+                Line::new(Point::new(-x, y0), Point::new(x, y0)),
+                Line::new(Point::new(x, y0), Point::new(x, y1)),
+                Line::new(Point::new(-x, y1), Point::new(x, y1)),
+                Line::new(Point::new(-x, y0), Point::new(-x, y1)),
+            ]
+        };
+        let mut expected = rect(0.5, -0.75, 0.75);
+        expected.extend(rect(0.125, -0.625, -0.375));
+        assert_lines_approx_eq(expected, projected.visible_lines());
+    }
+
+    #[test]
+    fn test_faces_passing_through_each_other() {
+        // A square facing the camera, and a narrow strip which tilts through it
+        // so that the top of the strip is in front and the bottom behind.
+        let mut scene = Scene3D::new();
+        scene.add_face(Face::new(vec![
+            Point3::new(-1., -1., 0.),
+            Point3::new(1., -1., 0.),
+            Point3::new(1., 1., 0.),
+            Point3::new(-1., 1., 0.),
+        ]));
+        scene.add_face(Face::new(vec![
+            Point3::new(-0.5, -1.5, -1.5),
+            Point3::new(0.5, -1.5, -1.5),
+            Point3::new(0.5, 1.5, 1.5),
+            Point3::new(-0.5, 1.5, 1.5),
+        ]));
+
+        let projected = scene
+            .project(&camera(Projection::Orthographic { height: 4. }))
+            .unwrap();
+
+        let line = |x1, y1, x2, y2| Line::new(Point::new(x1, y1), Point::new(x2, y2));
+        let expected = vec![
+            // The square, apart from where the strip passes in front of its top.
+            line(-0.5, -0.5, 0.5, -0.5),
+            line(-0.5, -0.5, -0.5, 0.5),
+            line(0.5, -0.5, 0.5, 0.5),
+            line(-0.5, 0.5, -0.25, 0.5),
+            line(0.25, 0.5, 0.5, 0.5),
+            // The strip, apart from where it passes behind the square.
+            line(-0.25, -0.75, 0.25, -0.75),
+            line(-0.25, 0.75, 0.25, 0.75),
+            line(-0.25, -0.75, -0.25, -0.5),
+            line(0.25, -0.75, 0.25, -0.5),
+            line(-0.25, 0., -0.25, 0.75),
+            line(0.25, 0., 0.25, 0.75),
+        ];
+        assert_lines_approx_eq(expected, projected.visible_lines());
+    }
+
+    #[test]
+    fn test_cyclic_overlap() {
+        // Three sticks, each resting on the next, seen from above. Each stick
+        // slopes up from its resting end to the end which lies on the next.
+        let stick = |from: Point3, to: Point3| {
+            let d = to - from;
+            let side = Point3::new(-d.y, d.x, 0.).normalize().unwrap() * 0.5;
+            Face::new(vec![from - side, to - side, to + side, from + side])
+        };
+        let corners: Vec<Point3> = (0..3)
+            .map(|i| {
+                let angle = std::f64::consts::PI * (0.5 + 2. * i as f64 / 3.);
+                Point3::new(4. * angle.cos(), 4. * angle.sin(), 0.)
+            })
+            .collect();
+
+        let mut scene = Scene3D::new();
+        for i in 0..3 {
+            // Extend each stick past its corners so that neighbours overlap.
+            let (a, b) = (corners[i], corners[(i + 1) % 3]);
+            let from = a.lerp(b, -0.2);
+            let to = a.lerp(b, 1.2);
+            scene.add_face(stick(
+                Point3::new(from.x, from.y, 0.),
+                Point3::new(to.x, to.y, 1.),
+            ));
+        }
+
+        let camera = Camera::look_at(
+            Point3::new(0., 0., 10.),
+            Point3::new(0., 0., 0.),
+            Point3::new(0., 1., 0.),
+            Projection::Orthographic { height: 20. },
+        )
+        .unwrap();
+        let projected = scene.project(&camera).unwrap();
+        assert_eq!(3, projected.polys().len());
+
+        // Where the sticks cross near each corner, the edges of the stick
+        // ending there are drawn over the stick starting there.
+        let visible = projected.visible_lines();
+        let on_visible_line = |point: Point3| {
+            let point = camera.project(point).unwrap().0;
+            visible.iter().any(|line| {
+                let d = line.end - line.start;
+                let t = ((point - line.start).dot(d) / d.dot(d)).clamp(0., 1.);
+                line.start.lerp(line.end, t).distance(point) < 1e-9
+            })
+        };
+        for i in 0..3 {
+            let corner = corners[(i + 1) % 3];
+            let ending = corners[i] - corner;
+            let starting = corners[(i + 2) % 3] - corner;
+            // Points on the edges of each stick, half its width from the corner.
+            let offset = |d: Point3| Point3::new(-d.y, d.x, 0.).normalize().unwrap() * 0.5;
+            for sign in &[-1., 1.] {
+                assert!(on_visible_line(corner + offset(ending) * *sign));
+                assert!(!on_visible_line(corner + offset(starting) * *sign));
+            }
+        }
+    }
+
+    #[test]
+    fn test_clipped_face() {
+        let mut scene = Scene3D::new();
+        scene.add_face(Face::new(vec![
+            Point3::new(-1., -1., 0.),
+            Point3::new(1., -1., 0.),
+            Point3::new(0., 1., 10.),
+        ]));
+
+        let camera = camera(Projection::Perspective { fov_y: 1. });
+        let projected = scene.project(&camera).unwrap();
+        assert_eq!(1, projected.polys().len());
+        assert_eq!(4, projected.polys()[0].points.len());
+
+        let projected = scene.project(&camera.with_near(6.).unwrap()).unwrap();
+        assert!(projected.polys().is_empty());
+    }
+
+    #[test]
+    fn test_non_finite_face() {
+        let mut scene = Scene3D::new();
+        scene.add_face(Face::new(vec![
+            Point3::new(0., 0., 0.),
+            Point3::new(1., f64::NAN, 0.),
+            Point3::new(0., 1., 0.),
+        ]));
+
+        let camera = camera(Projection::Orthographic { height: 4. });
+        assert!(matches!(
+            scene.project(&camera),
+            Err(Error::NonFinitePoint(_))
+        ));
+    }
+}
@@ -0,0 +1,549 @@
+use crate::affine::Affine2;
+use crate::error::Error;
+use crate::line::Line;
+use crate::point::Point;
+use crate::polygon::PolygonId;
+use crate::scene::Scene;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use svg::node::element;
+use svg::node::element::path::Data;
+use svg::Document;
+
+const INKSCAPE_NAMESPACE: &str = "http://www.inkscape.org/namespaces/inkscape";
+
+/// A unit of length on the page.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Unit {
+    Millimetres,
+    Inches,
+}
+
+impl Unit {
+    fn millimetres(self) -> f64 {
+        match self {
+            Unit::Millimetres => 1.,
+            Unit::Inches => 25.4,
+        }
+    }
+
+    fn suffix(self) -> &'static str {
+        match self {
+            Unit::Millimetres => "mm",
+            Unit::Inches => "in",
+        }
+    }
+}
+
+/// Writes the visible lines of a scene to an SVG file for plotting.
+///
+/// The lines are scaled to fit within the margins of the page, keeping their
+/// aspect ratio, and centred. Each line is stroked in the colour of the polygon
+/// it is an edge of, and lines of the same colour that join end to end are
+/// drawn as a single path. Polygons can be assigned to named layers, which are
+/// written as Inkscape layers; lines of other polygons are left outside them.
+#[derive(Clone, Debug)]
+pub struct SvgExporter {
+    // The page size is kept in the page's unit, and other lengths in
+    // millimetres.
+    page_width: f64,
+    page_height: f64,
+    unit: Unit,
+    margin: f64,
+    stroke_width: f64,
+    y_up: bool,
+    default_colour: String,
+    colours: HashMap<PolygonId, String>,
+    layers: Vec<String>,
+    polygon_layers: HashMap<PolygonId, usize>,
+}
+
+impl SvgExporter {
+    /// Returns an exporter for an A4 portrait page with 10mm margins, drawing
+    /// black lines 0.3mm wide.
+    pub fn new() -> SvgExporter {
+        SvgExporter {
+            page_width: 210.,
+            page_height: 297.,
+            unit: Unit::Millimetres,
+            margin: 10.,
+            stroke_width: 0.3,
+            y_up: false,
+            default_colour: "black".to_string(),
+            colours: HashMap::new(),
+            layers: Vec::new(),
+            polygon_layers: HashMap::new(),
+        }
+    }
+
+    /// Returns the exporter with the given page size. The document's
+    /// dimensions and coordinates are written in `unit`. Returns
+    /// `Error::InvalidPageLayout` unless the width and height are positive and
+    /// finite.
+    pub fn with_page_size(
+        mut self,
+        width: f64,
+        height: f64,
+        unit: Unit,
+    ) -> Result<SvgExporter, Error> {
+        if !(is_positive(width) && is_positive(height)) {
+            return Err(Error::InvalidPageLayout);
+        }
+        self.page_width = width;
+        self.page_height = height;
+        self.unit = unit;
+        Ok(self)
+    }
+
+    /// Returns the exporter with the given margin on each side of the page.
+    /// Returns `Error::InvalidPageLayout` if the margin is negative or not
+    /// finite. Writing fails if the margins leave no room on the page.
+    pub fn with_margin(mut self, margin: f64, unit: Unit) -> Result<SvgExporter, Error> {
+        if !(margin >= 0. && margin.is_finite()) {
+            return Err(Error::InvalidPageLayout);
+        }
+        self.margin = margin * unit.millimetres();
+        Ok(self)
+    }
+
+    /// Returns the exporter with the given stroke width. Returns
+    /// `Error::InvalidPageLayout` unless the width is positive and finite.
+    pub fn with_stroke_width(mut self, width: f64, unit: Unit) -> Result<SvgExporter, Error> {
+        if !is_positive(width) {
+            return Err(Error::InvalidPageLayout);
+        }
+        self.stroke_width = width * unit.millimetres();
+        Ok(self)
+    }
+
+    /// Returns the exporter with the scene's y axis pointing up the page, as
+    /// it does in scenes projected from a `Scene3D`, rather than down it as in
+    /// SVG.
+    pub fn with_y_up(mut self, y_up: bool) -> SvgExporter {
+        self.y_up = y_up;
+        self
+    }
+
+    /// Returns the exporter with the colour used for polygons which have not
+    /// been given one. Colours are written as given, so any SVG colour works.
+    pub fn with_default_colour(mut self, colour: &str) -> SvgExporter {
+        self.default_colour = colour.to_string();
+        self
+    }
+
+    /// Returns the exporter with the lines of polygon `id` drawn in `colour`.
+    pub fn with_colour(mut self, id: PolygonId, colour: &str) -> SvgExporter {
+        self.colours.insert(id, colour.to_string());
+        self
+    }
+
+    /// Returns the exporter with the lines of polygon `id` in the layer named
+    /// `layer`. Layers are written in the order they are first named.
+    pub fn with_layer(mut self, id: PolygonId, layer: &str) -> SvgExporter {
+        let index = match self.layers.iter().position(|name| name == layer) {
+            Some(index) => index,
+            None => {
+                self.layers.push(layer.to_string());
+                self.layers.len() - 1
+            }
+        };
+        self.polygon_layers.insert(id, index);
+        self
+    }
+
+    /// Write the visible lines of `scene` as SVG.
+    pub fn write<W: Write>(&self, scene: &Scene, writer: W) -> Result<(), Error> {
+        self.write_lines(&scene.visible_lines(), writer)
+    }
+
+    /// Write the visible lines of `scene` to an SVG file.
+    pub fn save<P: AsRef<Path>>(&self, scene: &Scene, path: P) -> Result<(), Error> {
+        self.write(scene, File::create(path)?)
+    }
+
+    /// Write `lines` as SVG, styled by the polygons they are marked with.
+    /// Returns `Error::EmptyDrawing` if there are no lines, and
+    /// `Error::InvalidPageLayout` if the margins leave no room on the page.
+    pub fn write_lines<W: Write>(&self, lines: &[Line], writer: W) -> Result<(), Error> {
+        svg::write(writer, &self.document(lines)?)?;
+        Ok(())
+    }
+
+    /// Returns the transform from scene coordinates to page coordinates, in
+    /// the page's unit, which fits `lines` within the margins.
+    fn page_transform(&self, lines: &[Line]) -> Result<Affine2, Error> {
+        let (width, height) = (self.page_width, self.page_height);
+        let margin = self.margin / self.unit.millimetres();
+        if 2. * margin >= width.min(height) {
+            return Err(Error::InvalidPageLayout);
+        }
+
+        let first = lines.first().ok_or(Error::EmptyDrawing)?.start;
+        let (mut min, mut max) = (first, first);
+        for point in lines.iter().flat_map(|line| vec![line.start, line.end]) {
+            min = Point::new(min.x.min(point.x), min.y.min(point.y));
+            max = Point::new(max.x.max(point.x), max.y.max(point.y));
+        }
+
+        // A drawing with no width or height is scaled to fit the other way.
+        let scales = [
+            (width - 2. * margin) / (max.x - min.x),
+            (height - 2. * margin) / (max.y - min.y),
+        ];
+        let scale = scales
+            .iter()
+            .copied()
+            .filter(|scale| scale.is_finite())
+            .fold(f64::INFINITY, f64::min);
+        let scale = if scale.is_finite() { scale } else { 1. };
+
+        let centre = min.lerp(max, 0.5);
+        let flip = if self.y_up { -1. } else { 1. };
+        Ok(Affine2::translate(-centre.x, -centre.y)
+            .then(&Affine2::scale(scale, scale * flip))
+            .then(&Affine2::translate(width / 2., height / 2.)))
+    }
+
+    fn document(&self, lines: &[Line]) -> Result<Document, Error> {
+        let transform = self.page_transform(lines)?;
+
+        // Join the lines of each layer and colour into a path, in the order
+        // they first appear.
+        let mut paths: Vec<(Option<usize>, &str, Data, Point)> = Vec::new();
+        let mut path_index: HashMap<(Option<usize>, &str), usize> = HashMap::new();
+        for line in lines {
+            let layer = line
+                .polygon
+                .and_then(|id| self.polygon_layers.get(&id))
+                .copied();
+            let colour = line
+                .polygon
+                .and_then(|id| self.colours.get(&id))
+                .unwrap_or(&self.default_colour);
+            let start = transform.apply(line.start);
+            let end = transform.apply(line.end);
+
+            match path_index.get(&(layer, colour.as_str())) {
+                Some(&index) => {
+                    let (_, _, data, last) = &mut paths[index];
+                    // Lines run left to right, so draw this one backwards if
+                    // that continues the path.
+                    let (from, to) = if *last == end {
+                        (end, start)
+                    } else {
+                        (start, end)
+                    };
+                    // The builder methods consume the data, so take it out of
+                    // the path rather than cloning it.
+                    let mut path_data = std::mem::take(data);
+                    if *last != from {
+                        path_data = path_data.move_to(from.coords());
+                    }
+                    *data = path_data.line_to(to.coords());
+                    *last = to;
+                }
+                None => {
+                    path_index.insert((layer, colour), paths.len());
+                    paths.push((
+                        layer,
+                        colour,
+                        Data::new().move_to(start.coords()).line_to(end.coords()),
+                        end,
+                    ));
+                }
+            }
+        }
+
+        let (width, height) = (self.page_width, self.page_height);
+        let suffix = self.unit.suffix();
+        let mut doc = Document::new()
+            .set("width", format!("{}{}", width, suffix))
+            .set("height", format!("{}{}", height, suffix))
+            .set("viewBox", (0., 0., width, height));
+        if !self.layers.is_empty() {
+            doc = doc.set("xmlns:inkscape", INKSCAPE_NAMESPACE);
+        }
+
+        let path_element = |colour: &str, data: &Data| {
+            element::Path::new()
+                .set("d", data.clone())
+                .set("fill", "none")
+                .set("stroke", colour)
+                .set("stroke-width", self.stroke_width / self.unit.millimetres())
+                .set("stroke-linecap", "round")
+                .set("stroke-linejoin", "round")
+        };
+        for (layer, colour, data, _) in &paths {
+            if layer.is_none() {
+                doc = doc.add(path_element(colour, data));
+            }
+        }
+        for (index, name) in self.layers.iter().enumerate() {
+            let mut group = element::Group::new()
+                .set("id", format!("layer{}", index + 1))
+                .set("inkscape:groupmode", "layer")
+                .set("inkscape:label", name.as_str());
+            for (layer, colour, data, _) in &paths {
+                if *layer == Some(index) {
+                    group = group.add(path_element(colour, data));
+                }
+            }
+            doc = doc.add(group);
+        }
+        Ok(doc)
+    }
+}
+
+/// Returns true if `value` is greater than zero and finite.
+fn is_positive(value: f64) -> bool {
+    value > 0. && value.is_finite()
+}
+
+impl Default for SvgExporter {
+    fn default() -> SvgExporter {
+        SvgExporter::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polygon::Polygon;
+    use svg::node::element::tag::Type;
+    use svg::parser::{Event, Parser};
+
+    fn square(x: f64, y: f64, size: f64, z: f64) -> Polygon {
+        Polygon::new(
+            vec![
+                Point::new(x, y),
+                Point::new(x + size, y),
+                Point::new(x + size, y + size),
+                Point::new(x, y + size),
+            ],
+            z,
+        )
+    }
+
+    fn write(exporter: &SvgExporter, scene: &Scene) -> String {
+        let mut output = Vec::new();
+        exporter.write(scene, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    /// Returns the attributes of the root element and of each path.
+    fn elements(content: &str) -> (HashMap<String, String>, Vec<HashMap<String, String>>) {
+        let mut root = HashMap::new();
+        let mut paths = Vec::new();
+        for event in Parser::new(content) {
+            if let Event::Tag(name, tag_type, attributes) = event {
+                let attributes = attributes
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.to_string()))
+                    .collect();
+                match (name, tag_type) {
+                    ("svg", Type::Start) => root = attributes,
+                    ("path", _) => paths.push(attributes),
+                    _ => (),
+                }
+            }
+        }
+        (root, paths)
+    }
+
+    /// Returns the points of a path's data, which is written with absolute
+    /// commands.
+    fn path_points(d: &str) -> Vec<Point> {
+        let numbers: Vec<f64> = d
+            .split(|c: char| c.is_whitespace() || c == ',' || c.is_ascii_alphabetic())
+            .filter(|token| !token.is_empty())
+            .map(|token| token.parse().unwrap())
+            .collect();
+        numbers
+            .chunks(2)
+            .map(|pair| Point::new(pair[0], pair[1]))
+            .collect()
+    }
+
+    #[test]
+    fn test_page() {
+        let mut scene = Scene::new();
+        scene.add_poly(square(0., 0., 1., 1.)).unwrap();
+
+        let (root, paths) = elements(&write(&SvgExporter::new(), &scene));
+        assert_eq!("210mm", root["width"]);
+        assert_eq!("297mm", root["height"]);
+        assert_eq!("0 0 210 297", root["viewBox"]);
+        assert_eq!(1, paths.len());
+        assert_eq!("black", paths[0]["stroke"]);
+        assert_eq!("0.3", paths[0]["stroke-width"]);
+
+        // The square fills the width between the margins, centred vertically.
+        let points = path_points(&paths[0]["d"]);
+        for corner in &[
+            Point::new(10., 53.5),
+            Point::new(200., 53.5),
+            Point::new(200., 243.5),
+            Point::new(10., 243.5),
+        ] {
+            assert!(
+                points.iter().any(|p| p.approx_eq(*corner, 1e-4)),
+                "{:?}",
+                points
+            );
+        }
+
+        let exporter = SvgExporter::new()
+            .with_page_size(8.5, 11., Unit::Inches)
+            .unwrap()
+            .with_margin(0.5, Unit::Inches)
+            .unwrap()
+            .with_stroke_width(0.5, Unit::Millimetres)
+            .unwrap();
+        let (root, paths) = elements(&write(&exporter, &scene));
+        assert_eq!("8.5in", root["width"]);
+        assert_eq!("11in", root["height"]);
+        assert_eq!("0 0 8.5 11", root["viewBox"]);
+        let stroke_width: f64 = paths[0]["stroke-width"].parse().unwrap();
+        assert!((stroke_width - 0.5 / 25.4).abs() < 1e-9);
+        let points = path_points(&paths[0]["d"]);
+        assert!(points
+            .iter()
+            .any(|p| p.approx_eq(Point::new(0.5, 1.75), 1e-6)));
+        assert!(points
+            .iter()
+            .any(|p| p.approx_eq(Point::new(8., 9.25), 1e-6)));
+    }
+
+    #[test]
+    fn test_y_up() {
+        let mut scene = Scene::new();
+        scene
+            .add_poly(Polygon::new(
+                vec![Point::new(0., 0.), Point::new(2., 0.), Point::new(1., 2.)],
+                1.,
+            ))
+            .unwrap();
+
+        let apex = |exporter: SvgExporter| {
+            let (_, paths) = elements(&write(
+                &exporter.with_margin(0., Unit::Millimetres).unwrap(),
+                &scene,
+            ));
+            let points = path_points(&paths[0]["d"]);
+            points
+                .into_iter()
+                .find(|p| (p.x - 105.).abs() < 1e-4)
+                .unwrap()
+        };
+        assert!((apex(SvgExporter::new()).y - 253.5).abs() < 1e-4);
+        assert!((apex(SvgExporter::new().with_y_up(true)).y - 43.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_colours_and_layers() {
+        let mut scene = Scene::new();
+        let back = scene.add_poly(square(0., 0., 10., 1.)).unwrap();
+        let middle = scene.add_poly(square(20., 0., 10., 1.)).unwrap();
+        let front = scene.add_poly(square(5., 5., 10., 2.)).unwrap();
+
+        // Without layers, there is a path for each colour.
+        let exporter = SvgExporter::new()
+            .with_colour(front, "red")
+            .with_default_colour("#00f");
+        let (root, paths) = elements(&write(&exporter, &scene));
+        assert!(!root.contains_key("xmlns:inkscape"));
+        let mut colours: Vec<&str> = paths.iter().map(|p| p["stroke"].as_str()).collect();
+        colours.sort();
+        assert_eq!(vec!["#00f", "red"], colours);
+
+        let output = write(
+            &exporter
+                .with_layer(front, "front")
+                .with_layer(back, "back")
+                .with_layer(middle, "back"),
+            &scene,
+        );
+        let (root, paths) = elements(&output);
+        assert_eq!(INKSCAPE_NAMESPACE, root["xmlns:inkscape"]);
+        assert_eq!(2, paths.len());
+        let front_layer = output.find(r#"inkscape:label="front""#).unwrap();
+        let back_layer = output.find(r#"inkscape:label="back""#).unwrap();
+        assert!(front_layer < back_layer);
+        assert!(output[front_layer..back_layer].contains(r#"stroke="red""#));
+        assert!(output[back_layer..].contains(r##"stroke="#00f""##));
+    }
+
+    #[test]
+    fn test_joined_paths() {
+        let lines = vec![
+            Line::new(Point::new(0., 0.), Point::new(1., 0.)),
+            Line::new(Point::new(1., 0.), Point::new(2., 1.)),
+            Line::new(Point::new(0., 1.), Point::new(2., 1.)),
+            Line::new(Point::new(3., 0.), Point::new(3., 1.)),
+        ];
+        let mut output = Vec::new();
+        SvgExporter::new().write_lines(&lines, &mut output).unwrap();
+        let (_, paths) = elements(&String::from_utf8(output).unwrap());
+
+        let d = &paths[0]["d"];
+        // The third line is drawn backwards from the end of the second, then
+        // the fourth starts a new subpath.
+        assert_eq!(2, d.matches('M').count(), "{}", d);
+        assert_eq!(4, d.matches('L').count(), "{}", d);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(
+            SvgExporter::new().write(&Scene::new(), Vec::new()),
+            Err(Error::EmptyDrawing)
+        ));
+
+        let mut scene = Scene::new();
+        scene.add_poly(square(0., 0., 1., 1.)).unwrap();
+        assert!(matches!(
+            SvgExporter::new().save(&scene, "no/such/directory/scene.svg"),
+            Err(Error::Io(_))
+        ));
+
+        let exporter = SvgExporter::new();
+        for &(width, height) in &[
+            (f64::NAN, 297.),
+            (210., f64::INFINITY),
+            (0., 297.),
+            (-210., 297.),
+        ] {
+            assert!(matches!(
+                exporter
+                    .clone()
+                    .with_page_size(width, height, Unit::Millimetres),
+                Err(Error::InvalidPageLayout)
+            ));
+        }
+        for &margin in &[-1., f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                exporter.clone().with_margin(margin, Unit::Millimetres),
+                Err(Error::InvalidPageLayout)
+            ));
+        }
+        for &width in &[0., -0.3, f64::NAN] {
+            assert!(matches!(
+                exporter.clone().with_stroke_width(width, Unit::Millimetres),
+                Err(Error::InvalidPageLayout)
+            ));
+        }
+
+        // The margins must leave room to draw on the page.
+        let exporter = exporter.with_margin(105., Unit::Millimetres).unwrap();
+        assert!(matches!(
+            exporter.write(&scene, Vec::new()),
+            Err(Error::InvalidPageLayout)
+        ));
+        let exporter = exporter
+            .with_page_size(211., 297., Unit::Millimetres)
+            .unwrap();
+        assert!(exporter.write(&scene, Vec::new()).is_ok());
+    }
+}
@@ -0,0 +1,850 @@
+use crate::affine::Affine2;
+use crate::error::Error;
+use crate::point::Point;
+use crate::polygon::{FillRule, Polygon};
+use crate::scene::Scene;
+use std::f64::consts::PI;
+use std::path::Path;
+use svg::node::element::path::{Command, Data, Position};
+use svg::node::element::tag::Type;
+use svg::node::Attributes;
+use svg::parser::{Event, Parser};
+
+/// The most straight lines that a single curve is flattened into, however small
+/// the tolerance is compared to the curve.
+const MAX_STEPS: usize = 10_000;
+
+/// Elements whose contents are not drawn where they appear.
+const HIDDEN_CONTAINERS: [&str; 6] = ["defs", "clipPath", "mask", "marker", "pattern", "symbol"];
+
+/// Reads the filled shapes of an SVG document into a `Scene`.
+///
+/// `<path>`, `<polygon>`, `<rect>`, `<circle>` and `<ellipse>` elements become
+/// polygons, with the `transform` of each element and its enclosing `<g>`
+/// elements applied. Each polygon is in front of those before it in the
+/// document. Shapes which enclose no area, such as straight lines, are skipped.
+/// Curves are replaced by straight lines which stay within a tolerance of
+/// them, measured after transformation. Coordinates are in the document's user
+/// units; `viewBox` and units such as `mm` are not supported, and path data is
+/// read with single precision.
+#[derive(Clone, Copy, Debug)]
+pub struct SvgImporter {
+    tolerance: f64,
+}
+
+impl SvgImporter {
+    pub fn new() -> SvgImporter {
+        SvgImporter { tolerance: 0.1 }
+    }
+
+    /// Returns the importer with the given tolerance for flattening curves. The
+    /// default is 0.1. Returns an error if the tolerance is not positive and
+    /// finite.
+    pub fn with_tolerance(mut self, tolerance: f64) -> Result<SvgImporter, Error> {
+        if !(0. < tolerance && tolerance.is_finite()) {
+            return Err(Error::InvalidTolerance(tolerance));
+        }
+        self.tolerance = tolerance;
+        Ok(self)
+    }
+
+    /// Read the SVG file at `path`.
+    pub fn load<P: AsRef<Path>>(&self, path: P) -> Result<Scene, Error> {
+        self.read_str(&std::fs::read_to_string(path)?)
+    }
+
+    /// Read an SVG document.
+    pub fn read_str(&self, content: &str) -> Result<Scene, Error> {
+        let mut scene = Scene::new();
+        // The transform and fill rule of each enclosing group.
+        let mut groups = vec![(Affine2::identity(), FillRule::NonZero)];
+        let mut hidden = 0;
+
+        for event in Parser::new(content) {
+            let (name, tag_type, attributes) = match event {
+                Event::Tag(name, tag_type, attributes) => (name, tag_type, attributes),
+                Event::Error(e) => return Err(Error::Parse(e.to_string())),
+                _ => continue,
+            };
+            if HIDDEN_CONTAINERS.contains(&name) {
+                match tag_type {
+                    Type::Start => hidden += 1,
+                    Type::End => hidden -= 1,
+                    Type::Empty => (),
+                }
+                continue;
+            }
+            // Groups inside hidden containers are never pushed, so they must not
+            // be popped either.
+            if hidden > 0 {
+                continue;
+            }
+            if tag_type == Type::End {
+                if name == "g" && groups.len() > 1 {
+                    groups.pop();
+                }
+                continue;
+            }
+
+            let (parent_transform, parent_fill_rule) = groups[groups.len() - 1];
+            let transform = match attributes.get("transform") {
+                Some(value) => parent_transform * parse_transform(value)?,
+                None => parent_transform,
+            };
+            let fill_rule = match attributes.get("fill-rule").map(|value| value.trim()) {
+                Some("evenodd") => FillRule::EvenOdd,
+                Some("nonzero") => FillRule::NonZero,
+                _ => parent_fill_rule,
+            };
+
+            if name == "g" {
+                if tag_type == Type::Start {
+                    groups.push((transform, fill_rule));
+                }
+                continue;
+            }
+
+            // Flatten in the element's own coordinates, to a tolerance scaled
+            // so that it holds once the element is transformed.
+            let tolerance = self.tolerance / max_scale(&transform);
+            let rings = match name {
+                "path" => path_rings(
+                    &Data::parse(attribute(&attributes, "d")?)
+                        .map_err(|e| Error::Parse(e.to_string()))?,
+                    tolerance,
+                ),
+                "polygon" => vec![parse_points(attribute(&attributes, "points")?)?],
+                "rect" => vec![rect_ring(&attributes, tolerance)?],
+                "circle" => {
+                    let r = length(&attributes, "r")?;
+                    vec![ellipse_ring(
+                        Point::new(length(&attributes, "cx")?, length(&attributes, "cy")?),
+                        r,
+                        r,
+                        tolerance,
+                    )]
+                }
+                "ellipse" => vec![ellipse_ring(
+                    Point::new(length(&attributes, "cx")?, length(&attributes, "cy")?),
+                    length(&attributes, "rx")?,
+                    length(&attributes, "ry")?,
+                    tolerance,
+                )],
+                _ => continue,
+            };
+            self.add_shape(&mut scene, rings, &transform, fill_rule)?;
+        }
+        Ok(scene)
+    }
+
+    fn add_shape(
+        &self,
+        scene: &mut Scene,
+        rings: Vec<Vec<Point>>,
+        transform: &Affine2,
+        fill_rule: FillRule,
+    ) -> Result<(), Error> {
+        let mut rings = rings.into_iter().filter(|ring| ring.len() >= 3);
+        let points = match rings.next() {
+            Some(points) => points,
+            None => return Ok(()),
+        };
+
+        let z = scene.polys().len() as f64;
+        let mut poly = Polygon::with_holes(points, rings.collect(), z).with_fill_rule(fill_rule);
+        poly.transform(transform);
+        poly.normalize();
+        if poly.validate().is_ok() {
+            scene.add_poly(poly)?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for SvgImporter {
+    fn default() -> SvgImporter {
+        SvgImporter::new()
+    }
+}
+
+/// Returns the largest factor by which `transform` stretches any vector.
+fn max_scale(transform: &Affine2) -> f64 {
+    let Affine2 { a, b, c, d, .. } = *transform;
+    let sum = a * a + b * b + c * c + d * d;
+    let det = a * d - b * c;
+    ((sum + (sum * sum - 4. * det * det).max(0.).sqrt()) / 2.).sqrt()
+}
+
+fn attribute<'a>(attributes: &'a Attributes, name: &str) -> Result<&'a str, Error> {
+    attributes
+        .get(name)
+        .map(|value| &**value)
+        .ok_or_else(|| Error::Parse(format!("missing attribute {:?}", name)))
+}
+
+/// Returns the value of a length attribute in user units, or zero if it is
+/// missing.
+fn length(attributes: &Attributes, name: &str) -> Result<f64, Error> {
+    match attributes.get(name) {
+        Some(value) => {
+            let value = value.trim();
+            value
+                .strip_suffix("px")
+                .unwrap_or(value)
+                .trim()
+                .parse()
+                .map_err(|_| Error::Parse(format!("unsupported length {:?} for {:?}", value, name)))
+        }
+        None => Ok(0.),
+    }
+}
+
+/// Parse a list of numbers separated by whitespace and/or commas. As in path
+/// data, a sign or a second decimal point also starts a new number, so `5-5`
+/// is two numbers.
+fn parse_numbers(value: &str) -> Result<Vec<f64>, Error> {
+    let mut tokens: Vec<String> = Vec::new();
+    let mut token = String::new();
+    for c in value.chars() {
+        let separator = c.is_whitespace() || c == ',';
+        let starts_number = match c {
+            '+' | '-' => !token.ends_with(['e', 'E']),
+            '.' => token.contains('.') && !token.contains(['e', 'E']),
+            _ => false,
+        };
+        if (separator || starts_number) && !token.is_empty() {
+            tokens.push(std::mem::take(&mut token));
+        }
+        if !separator {
+            token.push(c);
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+
+    tokens
+        .iter()
+        .map(|token| {
+            token
+                .parse()
+                .map_err(|_| Error::Parse(format!("invalid number {:?}", token)))
+        })
+        .collect()
+}
+
+fn parse_points(value: &str) -> Result<Vec<Point>, Error> {
+    let numbers = parse_numbers(value)?;
+    Ok(numbers
+        .chunks_exact(2)
+        .map(|pair| Point::new(pair[0], pair[1]))
+        .collect())
+}
+
+/// Parse the value of a `transform` attribute.
+fn parse_transform(value: &str) -> Result<Affine2, Error> {
+    let mut transform = Affine2::identity();
+    let mut rest = value.trim();
+    while !rest.is_empty() {
+        let invalid = || Error::Parse(format!("invalid transform {:?}", value));
+        let open = rest.find('(').ok_or_else(invalid)?;
+        let close = rest.find(')').ok_or_else(invalid)?;
+        if close < open {
+            return Err(invalid());
+        }
+        let name = rest[..open].trim();
+        let args = parse_numbers(&rest[open + 1..close])?;
+
+        let next = match (name, &args[..]) {
+            ("matrix", &[a, b, c, d, e, f]) => Affine2::new(a, b, c, d, e, f),
+            ("translate", &[x]) => Affine2::translate(x, 0.),
+            ("translate", &[x, y]) => Affine2::translate(x, y),
+            ("scale", &[s]) => Affine2::scale(s, s),
+            ("scale", &[x, y]) => Affine2::scale(x, y),
+            ("rotate", &[angle]) => Affine2::rotate(angle.to_radians()),
+            ("rotate", &[angle, x, y]) => {
+                Affine2::rotate_about(Point::new(x, y), angle.to_radians())
+            }
+            ("skewX", &[angle]) => Affine2::skew_x(angle.to_radians()),
+            ("skewY", &[angle]) => Affine2::skew_y(angle.to_radians()),
+            _ => return Err(invalid()),
+        };
+        transform = transform * next;
+        rest = rest[close + 1..].trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+    }
+    Ok(transform)
+}
+
+fn rect_ring(attributes: &Attributes, tolerance: f64) -> Result<Vec<Point>, Error> {
+    let x = length(attributes, "x")?;
+    let y = length(attributes, "y")?;
+    let width = length(attributes, "width")?;
+    let height = length(attributes, "height")?;
+
+    // A missing corner radius takes the value of the other one.
+    let (rx, ry) = match (attributes.get("rx"), attributes.get("ry")) {
+        (None, None) => (0., 0.),
+        (Some(_), None) => (length(attributes, "rx")?, length(attributes, "rx")?),
+        (None, Some(_)) => (length(attributes, "ry")?, length(attributes, "ry")?),
+        _ => (length(attributes, "rx")?, length(attributes, "ry")?),
+    };
+    let rx = rx.min(width / 2.);
+    let ry = ry.min(height / 2.);
+    if rx <= 0. || ry <= 0. {
+        return Ok(vec![
+            Point::new(x, y),
+            Point::new(x + width, y),
+            Point::new(x + width, y + height),
+            Point::new(x, y + height),
+        ]);
+    }
+
+    // Each corner is a quarter of an ellipse, starting from the top right.
+    let corners = [
+        (Point::new(x + width - rx, y + ry), -PI / 2.),
+        (Point::new(x + width - rx, y + height - ry), 0.),
+        (Point::new(x + rx, y + height - ry), PI / 2.),
+        (Point::new(x + rx, y + ry), PI),
+    ];
+    let steps = arc_steps(PI / 2., rx.max(ry), tolerance);
+    let mut ring = Vec::new();
+    for (centre, start) in &corners {
+        for i in 0..=steps {
+            let angle = start + PI / 2. * i as f64 / steps as f64;
+            ring.push(*centre + Point::new(rx * angle.cos(), ry * angle.sin()));
+        }
+    }
+    Ok(ring)
+}
+
+fn ellipse_ring(centre: Point, rx: f64, ry: f64, tolerance: f64) -> Vec<Point> {
+    if rx <= 0. || ry <= 0. {
+        return Vec::new();
+    }
+    let steps = arc_steps(2. * PI, rx.max(ry), tolerance).max(4);
+    (0..steps)
+        .map(|i| {
+            let angle = 2. * PI * i as f64 / steps as f64;
+            centre + Point::new(rx * angle.cos(), ry * angle.sin())
+        })
+        .collect()
+}
+
+/// Returns the number of straight lines needed to follow an arc of a circle of
+/// radius `r` through `angle` radians, without straying more than `tolerance`.
+fn arc_steps(angle: f64, r: f64, tolerance: f64) -> usize {
+    let step = if tolerance < r {
+        2. * (1. - tolerance / r).acos()
+    } else {
+        PI / 2.
+    };
+    // The step is zero when the tolerance is too small compared to the radius
+    // to make any difference to it.
+    if step.is_nan() || step <= 0. {
+        return MAX_STEPS;
+    }
+    step_count(angle.abs() / step)
+}
+
+/// Returns the number of straight lines needed to follow a Bézier curve whose
+/// second derivative is at most `max_second_derivative`, without straying more
+/// than `tolerance`.
+fn curve_steps(max_second_derivative: f64, tolerance: f64) -> usize {
+    step_count((max_second_derivative / (8. * tolerance)).sqrt())
+}
+
+/// Returns `steps` rounded up to a whole number between 1 and `MAX_STEPS`.
+fn step_count(steps: f64) -> usize {
+    if steps.is_nan() {
+        1
+    } else {
+        steps.ceil().clamp(1., MAX_STEPS as f64) as usize
+    }
+}
+
+/// Returns the rings of a path, one for each subpath, flattening curves to
+/// within `tolerance`. Subpaths which are not closed are closed with a straight
+/// line, as they are when they are filled.
+fn path_rings(data: &Data, tolerance: f64) -> Vec<Vec<Point>> {
+    let mut rings = Vec::new();
+    let mut ring: Vec<Point> = Vec::new();
+    let mut current = Point::new(0., 0.);
+    let mut start = current;
+    // The last control point of the previous command, if it was a curve of
+    // the same kind, for reflecting in smooth curves.
+    let mut last_cubic: Option<Point> = None;
+    let mut last_quadratic: Option<Point> = None;
+
+    for command in data.iter() {
+        let (position, parameters) = match command {
+            Command::Close => {
+                if ring.len() > 1 {
+                    rings.push(std::mem::take(&mut ring));
+                }
+                // Commands after a close continue a new subpath from the same
+                // start point.
+                ring = vec![start];
+                current = start;
+                last_cubic = None;
+                last_quadratic = None;
+                continue;
+            }
+            Command::Move(p, params)
+            | Command::Line(p, params)
+            | Command::HorizontalLine(p, params)
+            | Command::VerticalLine(p, params)
+            | Command::QuadraticCurve(p, params)
+            | Command::SmoothQuadraticCurve(p, params)
+            | Command::CubicCurve(p, params)
+            | Command::SmoothCubicCurve(p, params)
+            | Command::EllipticalArc(p, params) => (*p, params),
+        };
+        let params: Vec<f64> = parameters.iter().map(|&n| n as f64).collect();
+        let relative = position == Position::Relative;
+
+        let arity = match command {
+            Command::HorizontalLine(..) | Command::VerticalLine(..) => 1,
+            Command::Move(..) | Command::Line(..) | Command::SmoothQuadraticCurve(..) => 2,
+            Command::QuadraticCurve(..) | Command::SmoothCubicCurve(..) => 4,
+            Command::CubicCurve(..) => 6,
+            _ => 7,
+        };
+        for (i, args) in params.chunks_exact(arity).enumerate() {
+            let origin = if relative {
+                current
+            } else {
+                Point::new(0., 0.)
+            };
+            let point = |j: usize| origin + Point::new(args[j], args[j + 1]);
+
+            let mut cubic = None;
+            let mut quadratic = None;
+            match command {
+                Command::Move(..) if i == 0 => {
+                    if ring.len() > 1 {
+                        rings.push(std::mem::take(&mut ring));
+                    }
+                    current = point(0);
+                    start = current;
+                    ring = vec![current];
+                }
+                // Further pairs of coordinates after a move are lines.
+                Command::Move(..) | Command::Line(..) => {
+                    current = point(0);
+                    ring.push(current);
+                }
+                Command::HorizontalLine(..) => {
+                    current = Point::new(origin.x + args[0], current.y);
+                    ring.push(current);
+                }
+                Command::VerticalLine(..) => {
+                    current = Point::new(current.x, origin.y + args[0]);
+                    ring.push(current);
+                }
+                Command::QuadraticCurve(..) | Command::SmoothQuadraticCurve(..) => {
+                    let (control, end) = match command {
+                        Command::QuadraticCurve(..) => (point(0), point(2)),
+                        _ => (reflect(last_quadratic, current), point(0)),
+                    };
+                    let steps =
+                        curve_steps(2. * (current - control * 2. + end).length(), tolerance);
+                    for step in 1..=steps {
+                        let t = step as f64 / steps as f64;
+                        let a = current.lerp(control, t);
+                        ring.push(a.lerp(control.lerp(end, t), t));
+                    }
+                    current = end;
+                    quadratic = Some(control);
+                }
+                Command::CubicCurve(..) | Command::SmoothCubicCurve(..) => {
+                    let (c1, c2, end) = match command {
+                        Command::CubicCurve(..) => (point(0), point(2), point(4)),
+                        _ => (reflect(last_cubic, current), point(0), point(2)),
+                    };
+                    let dd = (current - c1 * 2. + c2)
+                        .length()
+                        .max((c1 - c2 * 2. + end).length());
+                    let steps = curve_steps(6. * dd, tolerance);
+                    for step in 1..=steps {
+                        let t = step as f64 / steps as f64;
+                        let (a, b, c) = (current.lerp(c1, t), c1.lerp(c2, t), c2.lerp(end, t));
+                        ring.push(a.lerp(b, t).lerp(b.lerp(c, t), t));
+                    }
+                    current = end;
+                    cubic = Some(c2);
+                }
+                _ => {
+                    let end = point(5);
+                    arc_points(current, end, args, tolerance, &mut ring);
+                    current = end;
+                }
+            }
+            last_cubic = cubic;
+            last_quadratic = quadratic;
+        }
+    }
+    if ring.len() > 1 {
+        rings.push(ring);
+    }
+
+    // A closing line back to the start of a ring is implied.
+    for ring in &mut rings {
+        if ring.len() > 1 && ring.first() == ring.last() {
+            ring.pop();
+        }
+    }
+    rings
+}
+
+/// Returns the reflection of `control` in `point`, or `point` if there is no
+/// control point.
+fn reflect(control: Option<Point>, point: Point) -> Point {
+    match control {
+        Some(control) => point * 2. - control,
+        None => point,
+    }
+}
+
+/// Add the points of an elliptical arc command with the given arguments, from
+/// `from` to `to`, to `ring`. See the SVG specification's implementation notes
+/// on converting arcs from endpoint to centre parameterization.
+fn arc_points(from: Point, to: Point, args: &[f64], tolerance: f64, ring: &mut Vec<Point>) {
+    let (mut rx, mut ry) = (args[0].abs(), args[1].abs());
+    let (sin, cos) = args[2].to_radians().sin_cos();
+    let (large_arc, sweep) = (args[3] != 0., args[4] != 0.);
+    if from == to {
+        return;
+    }
+    if rx == 0. || ry == 0. {
+        ring.push(to);
+        return;
+    }
+
+    let half = (from - to) * 0.5;
+    let p = Point::new(cos * half.x + sin * half.y, -sin * half.x + cos * half.y);
+    let lambda = (p.x / rx).powi(2) + (p.y / ry).powi(2);
+    if lambda > 1. {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+
+    let numerator = (rx * ry).powi(2) - (rx * p.y).powi(2) - (ry * p.x).powi(2);
+    let denominator = (rx * p.y).powi(2) + (ry * p.x).powi(2);
+    let sign = if large_arc != sweep { 1. } else { -1. };
+    let coef = sign * (numerator / denominator).max(0.).sqrt();
+    let c = Point::new(coef * rx * p.y / ry, -coef * ry * p.x / rx);
+    let centre = Point::new(cos * c.x - sin * c.y, sin * c.x + cos * c.y) + from.lerp(to, 0.5);
+
+    let start_angle = ((p.y - c.y) / ry).atan2((p.x - c.x) / rx);
+    let end_angle = ((-p.y - c.y) / ry).atan2((-p.x - c.x) / rx);
+    let mut delta = end_angle - start_angle;
+    if sweep && delta < 0. {
+        delta += 2. * PI;
+    } else if !sweep && delta > 0. {
+        delta -= 2. * PI;
+    }
+
+    let steps = arc_steps(delta, rx.max(ry), tolerance);
+    for step in 1..steps {
+        let angle = start_angle + delta * step as f64 / steps as f64;
+        let (x, y) = (rx * angle.cos(), ry * angle.sin());
+        ring.push(centre + Point::new(cos * x - sin * y, sin * x + cos * y));
+    }
+    ring.push(to);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::line::Line;
+    use crate::polygon::PolygonId;
+
+    fn read(content: &str) -> Scene {
+        SvgImporter::new().read_str(content).unwrap()
+    }
+
+    #[test]
+    fn test_parse_numbers() {
+        assert_eq!(
+            vec![0., 0., 10., 0., 5., -5.],
+            parse_numbers("0,0 10,0 5-5").unwrap()
+        );
+        assert_eq!(
+            vec![1.5, 0.5, -2e-3, 4e+1, 0.25],
+            parse_numbers(" 1.5.5-2e-3 , 4E+1+.25 ").unwrap()
+        );
+        assert!(parse_numbers("1 x 3").is_err());
+
+        let scene = read("<svg><polygon points='0,0 10,0 5-5'/></svg>");
+        assert_eq!(3, scene.polys()[0].points.len());
+    }
+
+    #[test]
+    fn test_parse_transform() {
+        let p = Point::new(1., 2.);
+        let apply = |value| parse_transform(value).unwrap().apply(p);
+
+        assert_eq!(Point::new(11., 22.), apply("translate(10, 20)"));
+        assert_eq!(Point::new(11., 2.), apply("translate(10)"));
+        assert_eq!(Point::new(3., 6.), apply("scale(3)"));
+        assert_eq!(Point::new(7., 14.), apply("matrix(1 0 0 2 6 10)"));
+        assert!(apply("rotate(90)").approx_eq(Point::new(-2., 1.), 1e-12));
+        assert!(apply("rotate(180 1 1)").approx_eq(Point::new(1., 0.), 1e-12));
+        assert!(apply("skewX(45)").approx_eq(Point::new(3., 2.), 1e-12));
+
+        // The rightmost transform is applied first.
+        assert_eq!(Point::new(12., 24.), apply("scale(2) translate(5, 10)"));
+        assert_eq!(Point::new(7., 14.), apply("translate(5,10),scale(2)"));
+
+        assert!(parse_transform("rotate(1, 2)").is_err());
+        assert!(parse_transform("spin(3)").is_err());
+    }
+
+    #[test]
+    fn test_shapes() {
+        let scene = read(
+            r#"<svg xmlns="http://www.w3.org/2000/svg">
+                <rect x="0" y="0" width="10" height="5" />
+                <polygon points="0,0 10,0 5,8" />
+                <circle cx="5" cy="5" r="2" />
+                <ellipse cx="5" cy="5" rx="4" ry="2" />
+                <path d="M 0 0 L 10 0 L 10 10 Z" />
+            </svg>"#,
+        );
+        let polys = scene.polys();
+        assert_eq!(5, polys.len());
+
+        assert_eq!(
+            vec![
+                Point::new(0., 0.),
+                Point::new(10., 0.),
+                Point::new(10., 5.),
+                Point::new(0., 5.)
+            ],
+            polys[0].points
+        );
+        assert_eq!(3, polys[1].points.len());
+        assert_eq!(3, polys[4].points.len());
+
+        // Later elements are in front.
+        for (i, poly) in polys.iter().enumerate() {
+            assert_eq!(i as f64, poly.z);
+        }
+
+        // Flattened curves stay within the tolerance.
+        for point in &polys[2].points {
+            assert!((point.distance(Point::new(5., 5.)) - 2.).abs() < 1e-9);
+        }
+        for window in polys[2].points.windows(2) {
+            let mid = window[0].lerp(window[1], 0.5);
+            assert!(2. - mid.distance(Point::new(5., 5.)) <= 0.1);
+        }
+    }
+
+    #[test]
+    fn test_tolerance() {
+        let content = r#"<svg><circle cx="0" cy="0" r="10" /></svg>"#;
+        let coarse = SvgImporter::new()
+            .with_tolerance(1.)
+            .unwrap()
+            .read_str(content)
+            .unwrap();
+        let fine = SvgImporter::new()
+            .with_tolerance(0.01)
+            .unwrap()
+            .read_str(content)
+            .unwrap();
+        assert!(coarse.polys()[0].points.len() < fine.polys()[0].points.len());
+
+        // The tolerance applies after transformation.
+        let scaled = SvgImporter::new()
+            .with_tolerance(1.)
+            .unwrap()
+            .read_str(r#"<svg><circle cx="0" cy="0" r="1" transform="scale(10)" /></svg>"#)
+            .unwrap();
+        assert_eq!(
+            coarse.polys()[0].points.len(),
+            scaled.polys()[0].points.len()
+        );
+    }
+
+    #[test]
+    fn test_invalid_tolerance() {
+        for tolerance in &[0., -1., f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                SvgImporter::new().with_tolerance(*tolerance),
+                Err(Error::InvalidTolerance(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_step_limit() {
+        // Scaled up this far, the tolerance is too small to matter in the
+        // circle's own coordinates, so the number of steps is capped.
+        let scene = read(r#"<svg><circle r="1" transform="scale(1e16)" /></svg>"#);
+        assert_eq!(MAX_STEPS, scene.polys()[0].points.len());
+
+        let scene = read(
+            r#"<svg><path d="M 0 0 Q 1 1 2 0 A 1 1 0 0 1 0 0 Z" transform="scale(1e300)" /></svg>"#,
+        );
+        let points = &scene.polys()[0].points;
+        assert!(2 < points.len() && points.len() <= 2 * MAX_STEPS);
+
+        assert_eq!(1, step_count(f64::NAN));
+        assert_eq!(MAX_STEPS, step_count(f64::INFINITY));
+        assert_eq!(1, step_count(0.));
+        assert_eq!(3, step_count(2.5));
+    }
+
+    #[test]
+    fn test_path_commands() {
+        let scene = read(
+            r#"<svg>
+                <path d="m 1 1 h 4 v 4 H 1 z" />
+                <path d="M 0 0 10 0 10 10" />
+                <path d="M 0 0 Q 5 10 10 0 T 20 0 L 20 -5 Z" />
+                <path d="M 0 0 C 0 5 10 5 10 0 S 20 -5 20 0 L 20 -5 Z" />
+                <path d="M 0 0 A 5 5 0 0 1 10 0 Z" />
+                <path d="M 0 0 L 10 0" />
+            </svg>"#,
+        );
+        let polys = scene.polys();
+        assert_eq!(5, polys.len());
+
+        assert_eq!(
+            vec![
+                Point::new(1., 1.),
+                Point::new(5., 1.),
+                Point::new(5., 5.),
+                Point::new(1., 5.)
+            ],
+            polys[0].points
+        );
+        // An open subpath is closed.
+        assert_eq!(3, polys[1].points.len());
+
+        // The smooth curves continue symmetrically from the end of the first.
+        for poly in &polys[2..4] {
+            let top = poly.points.iter().map(|p| p.y).fold(f64::MIN, f64::max);
+            let bottom = poly.points.iter().map(|p| p.y).fold(f64::MAX, f64::min);
+            assert!(top > 3. && bottom < -3., "{:?}", poly.points);
+        }
+
+        // A semicircle below the x axis, in SVG's y-down coordinates.
+        for point in &polys[4].points {
+            assert!((point.distance(Point::new(5., 0.)) - 5.).abs() < 1e-9);
+            assert!(point.y <= 1e-9);
+        }
+        assert!(polys[4].points.iter().any(|p| p.y < -4.9));
+    }
+
+    #[test]
+    fn test_commands_after_close() {
+        let data = Data::parse("M0 0 L10 0 L10 10 Z L0 10 L5 20 Z Z").unwrap();
+        assert_eq!(
+            vec![
+                vec![
+                    Point::new(0., 0.),
+                    Point::new(10., 0.),
+                    Point::new(10., 10.)
+                ],
+                vec![Point::new(0., 0.), Point::new(0., 10.), Point::new(5., 20.)],
+            ],
+            path_rings(&data, 0.1)
+        );
+    }
+
+    #[test]
+    fn test_groups() {
+        let scene = read(
+            r#"<svg>
+                <g transform="translate(10, 0)" fill-rule="evenodd">
+                    <g transform="scale(2)">
+                        <rect width="1" height="1" />
+                    </g>
+                    <rect width="1" height="1" fill-rule="nonzero" />
+                </g>
+                <rect width="1" height="1" />
+                <defs><rect width="5" height="5" /></defs>
+            </svg>"#,
+        );
+        let polys = scene.polys();
+        assert_eq!(3, polys.len());
+
+        assert_eq!(Point::new(12., 2.), polys[0].points[2]);
+        assert_eq!(FillRule::EvenOdd, polys[0].fill_rule);
+        assert_eq!(Point::new(11., 1.), polys[1].points[2]);
+        assert_eq!(FillRule::NonZero, polys[1].fill_rule);
+        assert_eq!(Point::new(1., 1.), polys[2].points[2]);
+    }
+
+    #[test]
+    fn test_groups_in_hidden_containers() {
+        let scene = read(
+            r#"<svg>
+                <g transform="translate(100, 0)">
+                    <defs><g><rect width="5" height="5" /></g></defs>
+                    <symbol><g transform="scale(3)"></g></symbol>
+                    <rect width="1" height="1" />
+                </g>
+            </svg>"#,
+        );
+        let polys = scene.polys();
+        assert_eq!(1, polys.len());
+        assert_eq!(Point::new(100., 0.), polys[0].points[0]);
+        assert_eq!(Point::new(101., 1.), polys[0].points[2]);
+    }
+
+    #[test]
+    fn test_holes() {
+        let scene = read(
+            r#"<svg><path fill-rule="evenodd" d="M0,0 H10 V10 H0 Z M3,3 H7 V7 H3 Z" /></svg>"#,
+        );
+        let poly = &scene.polys()[0];
+        assert_eq!(1, poly.holes.len());
+        assert_eq!(8, poly.lines.len());
+    }
+
+    #[test]
+    fn test_from_svg() {
+        let path = format!("{}/tests/fixtures/shapes.svg", env!("CARGO_MANIFEST_DIR"));
+        let scene = Scene::from_svg(path).unwrap();
+        assert_eq!(2, scene.polys().len());
+
+        // The square in front hides the corner of the one behind it.
+        let visible = scene.visible_lines();
+        let back = PolygonId(0);
+        assert!(visible.contains(&Line::new_with_poly(
+            Point::new(0., 0.),
+            Point::new(10., 0.),
+            back
+        )));
+        assert!(visible.contains(&Line::new_with_poly(
+            Point::new(10., 0.),
+            Point::new(10., 5.),
+            back
+        )));
+        assert!(!visible
+            .iter()
+            .any(|line| line.start == Point::new(10., 10.) || line.end == Point::new(10., 10.)));
+    }
+
+    #[test]
+    fn test_errors() {
+        let importer = SvgImporter::new();
+        assert!(matches!(
+            importer.read_str(r#"<svg><polygon /></svg>"#),
+            Err(Error::Parse(_))
+        ));
+        assert!(matches!(
+            importer.read_str(r#"<svg><circle r="1cm" /></svg>"#),
+            Err(Error::Parse(_))
+        ));
+        assert!(matches!(
+            importer.read_str(r#"<svg><rect transform="bad" /></svg>"#),
+            Err(Error::Parse(_))
+        ));
+        assert!(matches!(Scene::from_svg("missing.svg"), Err(Error::Io(_))));
+    }
+}
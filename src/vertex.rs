@@ -1,8 +1,8 @@
 use crate::line::Line;
 use crate::point::Point;
-use std::collections::BTreeSet;
-
-type LineCollection<'a> = BTreeSet<&'a Line>;
+/// Lines are collected by reference rather than deduplicated by value, since
+/// neighbouring polygons can have identical edges.
+type LineCollection<'a> = Vec<&'a Line>;
 
 /// Represents a point in space at which at least one line starts
 /// or ends. Multiple lines can start and end at the same vertex.
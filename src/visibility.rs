@@ -0,0 +1,839 @@
+use crate::line::{Line, LineOrientation};
+use crate::point::Point;
+use crate::polygon::{Polygon, PolygonId};
+use crate::scanlines::{LineEvent, ScanState};
+use crate::scene::Scene;
+use std::collections::HashMap;
+
+/// The y value of each line crossing a given x, from bottom to top, along with
+/// the polygons which contain the region directly above the line.
+type Profile<'a> = Vec<(f64, Vec<&'a Polygon>)>;
+
+/// Returns true if `other` is a polygon other than `poly` and is in front of it
+/// at `point`.
+fn in_front(other: &Polygon, poly: &Polygon, point: Point) -> bool {
+    !std::ptr::eq(other, poly) && other.z_at(point) > poly.z_at(point)
+}
+
+/// Returns true if any polygon in `inside` hides `poly` at `point`.
+fn occluded(inside: &[&Polygon], poly: &Polygon, point: Point) -> bool {
+    inside.iter().any(|other| in_front(other, poly, point))
+}
+
+/// Returns the polygons other than `poly` which contain the regions on both
+/// sides of one of its lines, and so hide the line wherever they are in front.
+fn covering<'a>(below: &[&'a Polygon], above: &[&'a Polygon], poly: &Polygon) -> Vec<&'a Polygon> {
+    below
+        .iter()
+        .copied()
+        .filter(|other| {
+            !std::ptr::eq(*other, poly) && above.iter().any(|o| std::ptr::eq(*o, *other))
+        })
+        .collect()
+}
+
+/// Returns true if `poly` is hidden at `point` according to the given profile.
+fn occluded_at(profile: &Profile, point: Point, poly: &Polygon) -> bool {
+    match profile.iter().rev().find(|(line_y, _)| *line_y < point.y) {
+        Some((_, inside)) => occluded(inside, poly, point),
+        None => false,
+    }
+}
+
+/// Returns the x value strictly between `x0` and `x1` at which `other` passes
+/// in front of or behind `poly` along `line`, if there is one. Depths vary
+/// linearly along the line, so there is at most one.
+fn depth_crossing(line: &Line, other: &Polygon, poly: &Polygon, x0: f64, x1: f64) -> Option<f64> {
+    let difference = |x: f64| {
+        let point = Point::new(x, line.y_at(x)?);
+        Some(other.z_at(point) - poly.z_at(point))
+    };
+    let (d0, d1) = (difference(x0)?, difference(x1)?);
+    if (d0 > 0.) == (d1 > 0.) || d0 == d1 {
+        return None;
+    }
+
+    let x = x0 + (x1 - x0) * d0 / (d0 - d1);
+    if x0 < x && x < x1 {
+        Some(x)
+    } else {
+        None
+    }
+}
+
+/// Runs a scan over a scene to completion, deciding which polygon is on top
+/// between each pair of adjacent lines and collecting the pieces of each line
+/// which are not hidden.
+pub struct VisibilitySweep<'a> {
+    scene: &'a Scene,
+    scan_state: ScanState<'a>,
+    /// For each active line that is currently visible, the point on the line at
+    /// which it became visible.
+    visible_from: HashMap<*const Line, Point>,
+    /// The active lines of the most recent span, from bottom to top.
+    span_lines: Vec<&'a Line>,
+    /// Vertical lines at the cursor's x, which are resolved once every event at
+    /// that x has been processed.
+    vertical_lines: Vec<&'a Line>,
+    /// Visible segments which have been completed so far.
+    visible: Vec<Line>,
+}
+
+impl<'a> VisibilitySweep<'a> {
+    pub fn new(scene: &'a Scene) -> VisibilitySweep<'a> {
+        VisibilitySweep {
+            scene,
+            scan_state: ScanState::new(scene),
+            visible_from: HashMap::new(),
+            span_lines: Vec::new(),
+            vertical_lines: Vec::new(),
+            visible: Vec::new(),
+        }
+    }
+
+    /// Run the sweep to completion and return the visible segments.
+    pub fn run(mut self) -> Vec<Line> {
+        while !self.scan_state.done() {
+            self.step();
+        }
+        self.visible
+    }
+
+    /// Process the next event, then update the visibility of every active line
+    /// for the span between this event and the next one.
+    fn step(&mut self) {
+        let events = self.scan_state.step();
+        let cursor = match self.scan_state.cursor {
+            Some(c) => c,
+            None => return,
+        };
+
+        for (line, line_event) in events {
+            match line_event {
+                LineEvent::Begin if line.is_vertical() => self.vertical_lines.push(line),
+                LineEvent::End => {
+                    if let Some(from) = self.visible_from.remove(&(line as *const Line)) {
+                        self.visible
+                            .push(Line::new_with_polygon_id(from, line.end, line.polygon));
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        // Several events can share an x coordinate; the span only exists once
+        // all of them have been processed.
+        let next = self.scan_state.next_point();
+        if next.is_none_or(|next| next.x > cursor.x) {
+            self.update_vertical_lines(cursor.x);
+            self.span_lines = self.scan_state.active.lines();
+            if let Some(next) = next {
+                self.update_span(cursor.x, next.x);
+            }
+        }
+    }
+
+    /// Returns the polygon which the given line is an edge of.
+    fn owner(&self, line: &Line) -> &'a Polygon {
+        line.polygon
+            .and_then(|id| self.scene.polygon(id))
+            .expect("Line does not belong to a polygon in the scene.")
+    }
+
+    /// Compute the profile of the given lines, ordered from bottom to top, at `x`.
+    fn profile(&self, lines: &[&'a Line], x: f64) -> Profile<'a> {
+        // The winding number of each polygon's rings around the region above
+        // the current line. Crossing a line which runs left to right winds
+        // once more around the regions above it.
+        let mut windings: Vec<(&'a Polygon, i32)> = Vec::new();
+
+        lines
+            .iter()
+            .map(|&line| {
+                let poly = self.owner(line);
+                let delta = match line.orientation {
+                    LineOrientation::LeftToRight => 1,
+                    LineOrientation::RightToLeft => -1,
+                };
+                match windings
+                    .iter_mut()
+                    .find(|(other, _)| std::ptr::eq(*other, poly))
+                {
+                    Some((_, winding)) => *winding += delta,
+                    None => windings.push((poly, delta)),
+                }
+
+                let inside = windings
+                    .iter()
+                    .filter(|(poly, winding)| poly.fill_rule.is_inside(*winding))
+                    .map(|(poly, _)| *poly)
+                    .collect();
+                (line.y_at(x).unwrap(), inside)
+            })
+            .collect()
+    }
+
+    /// Determine which active lines are visible in the span from `x0` to `x1`,
+    /// in which no lines begin, end or cross, opening and closing visible
+    /// segments within it.
+    fn update_span(&mut self, x0: f64, x1: f64) {
+        let active = self.scan_state.active.lines();
+        let profile = self.profile(&active, x0);
+
+        let mut group_start = 0;
+        while group_start < active.len() {
+            // Lines which lie on top of each other, such as the shared edge of
+            // two neighbouring polygons, are drawn as a single stroke.
+            let mut group_end = group_start + 1;
+            while group_end < active.len() && active[group_start].collinear(active[group_end]) {
+                group_end += 1;
+            }
+            let group = &active[group_start..group_end];
+
+            let below: &[&Polygon] = if group_start > 0 {
+                &profile[group_start - 1].1
+            } else {
+                &[]
+            };
+            let above = &profile[group_end - 1].1;
+
+            let members: Vec<(&Line, &Polygon, Vec<&Polygon>)> = group
+                .iter()
+                .map(|&line| {
+                    let poly = self.owner(line);
+                    (line, poly, covering(below, above, poly))
+                })
+                .collect();
+
+            // The regions on either side of each line don't change within the
+            // span, but the polygons covering a line can pass in front of or
+            // behind it, so split the span where they do.
+            let mut xs = vec![x0, x1];
+            for (line, poly, covering) in &members {
+                xs.extend(
+                    covering
+                        .iter()
+                        .filter_map(|other| depth_crossing(line, other, poly, x0, x1)),
+                );
+            }
+            xs.sort_by(|a, b| a.total_cmp(b));
+            xs.dedup();
+
+            for piece in xs.windows(2) {
+                let mid = (piece[0] + piece[1]) / 2.;
+                let visible: Vec<&Line> = members
+                    .iter()
+                    .filter(|(line, poly, covering)| {
+                        let point = Point::new(mid, line.y_at(mid).unwrap());
+                        !occluded(covering, poly, point)
+                    })
+                    .map(|(line, _, _)| *line)
+                    .collect();
+
+                // Prefer a line which is already visible, so that its segment continues.
+                let stroke = visible
+                    .iter()
+                    .find(|&&line| self.visible_from.contains_key(&(line as *const Line)))
+                    .or_else(|| visible.first());
+
+                let point = if piece[0] == x0 {
+                    Point::new(x0, profile[group_start].0)
+                } else {
+                    Point::new(piece[0], group[0].y_at(piece[0]).unwrap())
+                };
+                for &line in group {
+                    let key = line as *const Line;
+                    if stroke.is_some_and(|&stroke| std::ptr::eq(stroke, line)) {
+                        self.visible_from.entry(key).or_insert(point);
+                    } else if let Some(from) = self.visible_from.remove(&key) {
+                        self.visible
+                            .push(Line::new_with_polygon_id(from, point, line.polygon));
+                    }
+                }
+            }
+
+            group_start = group_end;
+        }
+    }
+
+    /// Determine which parts of the vertical lines at `x` are visible. A part is
+    /// hidden if it is hidden both just to the left and just to the right of `x`.
+    /// Where vertical lines overlap, the visible parts are drawn once.
+    fn update_vertical_lines(&mut self, x: f64) {
+        let left = self.profile(&self.span_lines, x);
+        let right = self.profile(&self.scan_state.active.lines(), x);
+
+        let mut intervals: Vec<(f64, f64, Option<PolygonId>)> = Vec::new();
+        for line in std::mem::take(&mut self.vertical_lines) {
+            let poly = self.owner(line);
+
+            // The line may be hidden or not between each pair of lines crossing
+            // it, and where another polygon passes in front of or behind it.
+            let crossings = left
+                .iter()
+                .chain(right.iter())
+                .flat_map(|(_, inside)| inside.iter())
+                .filter_map(|other| {
+                    let dz = other.z - poly.z + (other.z_gradient.x - poly.z_gradient.x) * x;
+                    let dz_dy = other.z_gradient.y - poly.z_gradient.y;
+                    if dz_dy != 0. {
+                        Some(-dz / dz_dy)
+                    } else {
+                        None
+                    }
+                });
+            let mut ys: Vec<f64> = left
+                .iter()
+                .chain(right.iter())
+                .map(|(y, _)| *y)
+                .chain(crossings)
+                .filter(|y| line.start.y < *y && *y < line.end.y)
+                .collect();
+            ys.push(line.start.y);
+            ys.push(line.end.y);
+            ys.sort_by(|a, b| a.total_cmp(b));
+            ys.dedup();
+
+            let mut visible_from: Option<f64> = None;
+            for pair in ys.windows(2) {
+                let mid = Point::new(x, (pair[0] + pair[1]) / 2.);
+                let hidden = occluded_at(&left, mid, poly) && occluded_at(&right, mid, poly);
+                match visible_from {
+                    None if !hidden => visible_from = Some(pair[0]),
+                    Some(from) if hidden => {
+                        intervals.push((from, pair[0], line.polygon));
+                        visible_from = None;
+                    }
+                    _ => (),
+                }
+            }
+            if let Some(from) = visible_from {
+                intervals.push((from, line.end.y, line.polygon));
+            }
+        }
+
+        intervals.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut merged: Vec<(f64, f64, Option<PolygonId>)> = Vec::with_capacity(intervals.len());
+        for (low, high, polygon) in intervals {
+            match merged.last_mut() {
+                // Where edges of different polygons overlap, the one that
+                // starts first owns the overlap and the other continues past it.
+                Some(last) if low < last.1 => {
+                    if high <= last.1 {
+                        continue;
+                    } else if polygon == last.2 {
+                        last.1 = high;
+                    } else {
+                        let from = last.1;
+                        merged.push((from, high, polygon));
+                    }
+                }
+                _ => merged.push((low, high, polygon)),
+            }
+        }
+        for (low, high, polygon) in merged {
+            self.visible.push(Line::new_with_polygon_id(
+                Point::new(x, low),
+                Point::new(x, high),
+                polygon,
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polygon::FillRule;
+
+    fn diamond(cx: f64, cy: f64, r: f64, z: f64) -> Polygon {
+        Polygon::new(
+            vec![
+                Point::new(cx - r, cy),
+                Point::new(cx, cy + r),
+                Point::new(cx + r, cy),
+                Point::new(cx, cy - r),
+            ],
+            z,
+        )
+    }
+
+    fn diamond_lines(cx: f64, cy: f64, r: f64) -> Vec<Line> {
+        vec![
+            Line::new(Point::new(cx - r, cy), Point::new(cx, cy + r)),
+            Line::new(Point::new(cx, cy + r), Point::new(cx + r, cy)),
+            Line::new(Point::new(cx + r, cy), Point::new(cx, cy - r)),
+            Line::new(Point::new(cx, cy - r), Point::new(cx - r, cy)),
+        ]
+    }
+
+    /// Returns the visible lines, sorted and without the ids of their polygons.
+    fn visible_sorted(scene: &Scene) -> Vec<Line> {
+        let mut lines: Vec<Line> = scene
+            .visible_lines()
+            .iter()
+            .map(|line| Line::new(line.start, line.end))
+            .collect();
+        lines.sort();
+        lines
+    }
+
+    #[test]
+    fn test_single_polygon() {
+        let mut scene = Scene::new();
+        scene.add_poly(diamond(10., 10., 5., 1.)).unwrap();
+
+        let mut expected = diamond_lines(10., 10., 5.);
+        expected.sort();
+        assert_eq!(expected, visible_sorted(&scene));
+    }
+
+    #[test]
+    fn test_front_polygon_inside_back_polygon() {
+        let mut scene = Scene::new();
+        scene.add_poly(diamond(10., 10., 5., 1.)).unwrap();
+        scene.add_poly(diamond(10., 10., 2., 2.)).unwrap();
+
+        let mut expected = diamond_lines(10., 10., 5.);
+        expected.append(&mut diamond_lines(10., 10., 2.));
+        expected.sort();
+        assert_eq!(expected, visible_sorted(&scene));
+    }
+
+    #[test]
+    fn test_back_polygon_inside_front_polygon() {
+        let mut scene = Scene::new();
+        scene.add_poly(diamond(10., 10., 5., 2.)).unwrap();
+        scene.add_poly(diamond(10., 10., 2., 1.)).unwrap();
+
+        let mut expected = diamond_lines(10., 10., 5.);
+        expected.sort();
+        assert_eq!(expected, visible_sorted(&scene));
+    }
+
+    #[test]
+    fn test_overlapping_polygons() {
+        let mut scene = Scene::new();
+        scene.add_poly(diamond(10., 10., 5., 1.)).unwrap();
+        scene.add_poly(diamond(15., 10., 5., 2.)).unwrap();
+
+        let mut expected = diamond_lines(15., 10., 5.);
+        expected.append(&mut vec![
+            Line::new(Point::new(5., 10.), Point::new(10., 15.)),
+            Line::new(Point::new(10., 15.), Point::new(12.5, 12.5)),
+            Line::new(Point::new(5., 10.), Point::new(10., 5.)),
+            Line::new(Point::new(10., 5.), Point::new(12.5, 7.5)),
+        ]);
+        expected.sort();
+        assert_eq!(expected, visible_sorted(&scene));
+    }
+
+    fn rect(x1: f64, y1: f64, x2: f64, y2: f64, z: f64) -> Polygon {
+        Polygon::new(
+            vec![
+                Point::new(x1, y1),
+                Point::new(x2, y1),
+                Point::new(x2, y2),
+                Point::new(x1, y2),
+            ],
+            z,
+        )
+    }
+
+    #[test]
+    fn test_overlapping_rects() {
+        let mut scene = Scene::new();
+        scene.add_poly(rect(0., 0., 10., 10., 1.)).unwrap();
+        scene.add_poly(rect(5., 5., 15., 15., 2.)).unwrap();
+
+        let mut expected = vec![
+            // Front rectangle.
+            Line::new(Point::new(5., 5.), Point::new(15., 5.)),
+            Line::new(Point::new(15., 5.), Point::new(15., 15.)),
+            Line::new(Point::new(15., 15.), Point::new(5., 15.)),
+            Line::new(Point::new(5., 15.), Point::new(5., 5.)),
+            // Visible parts of the back rectangle.
+            Line::new(Point::new(0., 0.), Point::new(10., 0.)),
+            Line::new(Point::new(10., 0.), Point::new(10., 5.)),
+            Line::new(Point::new(0., 10.), Point::new(5., 10.)),
+            Line::new(Point::new(0., 0.), Point::new(0., 10.)),
+        ];
+        expected.sort();
+        assert_eq!(expected, visible_sorted(&scene));
+    }
+
+    #[test]
+    fn test_visible_lines_have_polygon_ids() {
+        let mut scene = Scene::new();
+        let back = scene.add_poly(rect(0., 0., 10., 10., 1.)).unwrap();
+        let front = scene.add_poly(rect(5., 5., 15., 15., 2.)).unwrap();
+
+        let visible = scene.visible_lines();
+        assert_eq!(8, visible.len());
+        // Lines ending in the sweep and vertical lines both keep the id.
+        assert!(visible.contains(&Line::new_with_poly(
+            Point::new(0., 0.),
+            Point::new(10., 0.),
+            back
+        )));
+        assert!(visible.contains(&Line::new_with_poly(
+            Point::new(10., 0.),
+            Point::new(10., 5.),
+            back
+        )));
+        assert!(visible.contains(&Line::new_with_poly(
+            Point::new(5., 5.),
+            Point::new(5., 15.),
+            front
+        )));
+    }
+
+    #[test]
+    fn test_overlapping_vertical_edges_keep_polygon_ids() {
+        let mut scene = Scene::new();
+        let a = scene.add_poly(rect(0., 0., 10., 5., 1.)).unwrap();
+        let b = scene.add_poly(rect(10., 3., 20., 10., 1.)).unwrap();
+
+        let mut shared: Vec<Line> = scene
+            .visible_lines()
+            .into_iter()
+            .filter(|line| line.start.x == 10. && line.end.x == 10.)
+            .collect();
+        shared.sort();
+        assert_eq!(
+            vec![
+                Line::new_with_poly(Point::new(10., 0.), Point::new(10., 5.), a),
+                Line::new_with_poly(Point::new(10., 5.), Point::new(10., 10.), b),
+            ],
+            shared
+        );
+    }
+
+    #[test]
+    fn test_vertical_edge_inside_front_polygon() {
+        let mut scene = Scene::new();
+        scene.add_poly(diamond(10., 10., 10., 2.)).unwrap();
+        scene.add_poly(rect(8., 8., 12., 12., 1.)).unwrap();
+
+        let mut expected = diamond_lines(10., 10., 10.);
+        expected.sort();
+        assert_eq!(expected, visible_sorted(&scene));
+    }
+
+    #[test]
+    fn test_shared_vertical_edge() {
+        let mut scene = Scene::new();
+        scene.add_poly(rect(0., 0., 10., 10., 1.)).unwrap();
+        scene.add_poly(rect(10., 0., 20., 10., 1.)).unwrap();
+
+        let mut expected = vec![
+            Line::new(Point::new(0., 0.), Point::new(10., 0.)),
+            Line::new(Point::new(10., 0.), Point::new(20., 0.)),
+            Line::new(Point::new(0., 10.), Point::new(10., 10.)),
+            Line::new(Point::new(10., 10.), Point::new(20., 10.)),
+            Line::new(Point::new(0., 0.), Point::new(0., 10.)),
+            Line::new(Point::new(10., 0.), Point::new(10., 10.)),
+            Line::new(Point::new(20., 0.), Point::new(20., 10.)),
+        ];
+        expected.sort();
+        assert_eq!(expected, visible_sorted(&scene));
+    }
+
+    #[test]
+    fn test_shared_sloped_edge() {
+        // Two triangles tiling a square, sharing its diagonal.
+        let mut scene = Scene::new();
+        let (a, b, c, d) = (
+            Point::new(0., 0.),
+            Point::new(10., 0.),
+            Point::new(10., 10.),
+            Point::new(0., 10.),
+        );
+        scene.add_poly(Polygon::new(vec![a, b, c], 1.)).unwrap();
+        scene.add_poly(Polygon::new(vec![a, c, d], 2.)).unwrap();
+
+        let mut expected = vec![
+            Line::new(a, b),
+            Line::new(b, c),
+            Line::new(c, d),
+            Line::new(d, a),
+            Line::new(a, c),
+        ];
+        expected.sort();
+        assert_eq!(expected, visible_sorted(&scene));
+    }
+
+    #[test]
+    fn test_shared_edges_behind_polygon() {
+        // A front polygon hides the shared edges of a tiled mesh behind it.
+        let mut scene = Scene::new();
+        scene.add_poly(rect(0., 0., 10., 10., 1.)).unwrap();
+        scene.add_poly(rect(10., 0., 20., 10., 1.)).unwrap();
+        scene.add_poly(rect(-5., -5., 25., 15., 2.)).unwrap();
+
+        let mut expected = vec![
+            Line::new(Point::new(-5., -5.), Point::new(25., -5.)),
+            Line::new(Point::new(-5., 15.), Point::new(25., 15.)),
+            Line::new(Point::new(-5., -5.), Point::new(-5., 15.)),
+            Line::new(Point::new(25., -5.), Point::new(25., 15.)),
+        ];
+        expected.sort();
+        assert_eq!(expected, visible_sorted(&scene));
+    }
+
+    #[test]
+    fn test_disjoint_polygons() {
+        let mut scene = Scene::new();
+        scene.add_poly(diamond(0., 0., 2., 2.)).unwrap();
+        scene.add_poly(diamond(10., 0., 2., 1.)).unwrap();
+
+        let mut expected = diamond_lines(0., 0., 2.);
+        expected.append(&mut diamond_lines(10., 0., 2.));
+        expected.sort();
+        assert_eq!(expected, visible_sorted(&scene));
+    }
+
+    /// A square frame from (0, 0) to (10, 10) with a square hole from (3, 3) to (7, 7).
+    fn frame(z: f64) -> Polygon {
+        Polygon::with_holes(
+            rect(0., 0., 10., 10., z).points,
+            vec![rect(3., 3., 7., 7., z).points],
+            z,
+        )
+    }
+
+    fn frame_lines() -> Vec<Line> {
+        vec![
+            Line::new(Point::new(0., 0.), Point::new(10., 0.)),
+            Line::new(Point::new(10., 0.), Point::new(10., 10.)),
+            Line::new(Point::new(0., 10.), Point::new(10., 10.)),
+            Line::new(Point::new(0., 0.), Point::new(0., 10.)),
+            Line::new(Point::new(3., 3.), Point::new(7., 3.)),
+            Line::new(Point::new(7., 3.), Point::new(7., 7.)),
+            Line::new(Point::new(3., 7.), Point::new(7., 7.)),
+            Line::new(Point::new(3., 3.), Point::new(3., 7.)),
+        ]
+    }
+
+    #[test]
+    fn test_polygon_seen_through_hole() {
+        let mut scene = Scene::new();
+        scene.add_poly(frame(2.)).unwrap();
+        scene.add_poly(diamond(5., 5., 1., 1.)).unwrap();
+
+        let mut expected = frame_lines();
+        expected.append(&mut diamond_lines(5., 5., 1.));
+        expected.sort();
+        assert_eq!(expected, visible_sorted(&scene));
+    }
+
+    #[test]
+    fn test_polygon_partly_behind_hole() {
+        let mut scene = Scene::new();
+        scene.add_poly(frame(2.)).unwrap();
+        scene.add_poly(rect(5., 5., 8., 8., 1.)).unwrap();
+
+        let mut expected = frame_lines();
+        expected.append(&mut vec![
+            Line::new(Point::new(5., 5.), Point::new(7., 5.)),
+            Line::new(Point::new(5., 5.), Point::new(5., 7.)),
+        ]);
+        expected.sort();
+        assert_eq!(expected, visible_sorted(&scene));
+    }
+
+    #[test]
+    fn test_polygon_in_front_of_hole() {
+        let mut scene = Scene::new();
+        scene.add_poly(frame(1.)).unwrap();
+        scene.add_poly(rect(2., 2., 5., 5., 2.)).unwrap();
+
+        let mut expected = vec![
+            // Front rectangle.
+            Line::new(Point::new(2., 2.), Point::new(5., 2.)),
+            Line::new(Point::new(5., 2.), Point::new(5., 5.)),
+            Line::new(Point::new(2., 5.), Point::new(5., 5.)),
+            Line::new(Point::new(2., 2.), Point::new(2., 5.)),
+            // Frame, apart from the corner of the hole behind the rectangle.
+            Line::new(Point::new(0., 0.), Point::new(10., 0.)),
+            Line::new(Point::new(10., 0.), Point::new(10., 10.)),
+            Line::new(Point::new(0., 10.), Point::new(10., 10.)),
+            Line::new(Point::new(0., 0.), Point::new(0., 10.)),
+            Line::new(Point::new(5., 3.), Point::new(7., 3.)),
+            Line::new(Point::new(7., 3.), Point::new(7., 7.)),
+            Line::new(Point::new(3., 7.), Point::new(7., 7.)),
+            Line::new(Point::new(3., 5.), Point::new(3., 7.)),
+        ];
+        expected.sort();
+        assert_eq!(expected, visible_sorted(&scene));
+    }
+
+    /// A five-pointed star drawn with a single self-intersecting ring, centred on (10, 10).
+    fn star(z: f64) -> Polygon {
+        let points = (0..5)
+            .map(|i| {
+                let angle = std::f64::consts::PI * (0.5 + 0.8 * i as f64);
+                Point::new(10. + 10. * angle.cos(), 10. + 10. * angle.sin())
+            })
+            .collect();
+        Polygon::new(points, z)
+    }
+
+    fn is_visible(scene: &Scene, lines: &[Line]) -> bool {
+        let visible = visible_sorted(scene);
+        lines.iter().all(|line| visible.contains(line))
+    }
+
+    #[test]
+    fn test_star_even_odd() {
+        // The centre of the star is crossed twice, so it is outside.
+        let mut scene = Scene::new();
+        scene
+            .add_poly(star(2.).with_fill_rule(FillRule::EvenOdd))
+            .unwrap();
+        scene.add_poly(diamond(10., 10., 1., 1.)).unwrap();
+
+        assert!(is_visible(&scene, &diamond_lines(10., 10., 1.)));
+    }
+
+    #[test]
+    fn test_star_nonzero() {
+        // The star winds around its centre twice, so it is inside.
+        let mut scene = Scene::new();
+        scene
+            .add_poly(star(2.).with_fill_rule(FillRule::NonZero))
+            .unwrap();
+        scene.add_poly(diamond(10., 10., 1., 1.)).unwrap();
+
+        let visible = scene.visible_lines();
+        for line in diamond_lines(10., 10., 1.) {
+            assert!(!visible.iter().any(|v| v.collinear(&line)));
+        }
+    }
+
+    #[test]
+    fn test_nonzero_hole_orientation() {
+        // An inner ring wound the same way as the outer ring doesn't cut a hole
+        // under the nonzero rule, but one wound the opposite way does.
+        let outer = rect(0., 0., 10., 10., 2.).points;
+        let inner = rect(3., 3., 7., 7., 2.).points;
+        let mut reversed = inner.clone();
+        reversed.reverse();
+
+        let mut scene = Scene::new();
+        scene
+            .add_poly(
+                Polygon::with_holes(outer.clone(), vec![inner], 2.)
+                    .with_fill_rule(FillRule::NonZero),
+            )
+            .unwrap();
+        scene.add_poly(diamond(5., 5., 1., 1.)).unwrap();
+        assert!(!is_visible(&scene, &diamond_lines(5., 5., 1.)));
+
+        let mut scene = Scene::new();
+        scene
+            .add_poly(
+                Polygon::with_holes(outer, vec![reversed], 2.).with_fill_rule(FillRule::NonZero),
+            )
+            .unwrap();
+        scene.add_poly(diamond(5., 5., 1., 1.)).unwrap();
+        assert!(is_visible(&scene, &diamond_lines(5., 5., 1.)));
+    }
+
+    #[test]
+    fn test_polygons_crossing_in_depth() {
+        // The back rectangle comes forward from left to right, passing in front
+        // of the middle of the front rectangle.
+        let mut scene = Scene::new();
+        scene
+            .add_poly(rect(0., 0., 10., 10., 0.).with_z_gradient(Point::new(1., 0.)))
+            .unwrap();
+        scene.add_poly(rect(2., 2., 8., 8., 5.)).unwrap();
+
+        let mut expected = vec![
+            Line::new(Point::new(0., 0.), Point::new(10., 0.)),
+            Line::new(Point::new(10., 0.), Point::new(10., 10.)),
+            Line::new(Point::new(0., 10.), Point::new(10., 10.)),
+            Line::new(Point::new(0., 0.), Point::new(0., 10.)),
+            Line::new(Point::new(2., 2.), Point::new(5., 2.)),
+            Line::new(Point::new(2., 8.), Point::new(5., 8.)),
+            Line::new(Point::new(2., 2.), Point::new(2., 8.)),
+        ];
+        expected.sort();
+        assert_eq!(expected, visible_sorted(&scene));
+    }
+
+    #[test]
+    fn test_vertical_lines_crossing_in_depth() {
+        let mut scene = Scene::new();
+        scene
+            .add_poly(rect(0., 0., 10., 10., 0.).with_z_gradient(Point::new(0., 1.)))
+            .unwrap();
+        scene.add_poly(rect(2., 2., 8., 8., 5.)).unwrap();
+
+        let mut expected = vec![
+            Line::new(Point::new(0., 0.), Point::new(10., 0.)),
+            Line::new(Point::new(10., 0.), Point::new(10., 10.)),
+            Line::new(Point::new(0., 10.), Point::new(10., 10.)),
+            Line::new(Point::new(0., 0.), Point::new(0., 10.)),
+            Line::new(Point::new(2., 2.), Point::new(8., 2.)),
+            Line::new(Point::new(2., 2.), Point::new(2., 5.)),
+            Line::new(Point::new(8., 2.), Point::new(8., 5.)),
+        ];
+        expected.sort();
+        assert_eq!(expected, visible_sorted(&scene));
+    }
+
+    /// Returns true if `point` lies on one of `lines`.
+    fn on_line(lines: &[Line], point: Point) -> bool {
+        lines.iter().any(|line| {
+            crate::predicates::orient2d(line.start, line.end, point) == 0.
+                && line.start <= point
+                && point <= line.end
+        })
+    }
+
+    #[test]
+    fn test_cyclic_overlap() {
+        // Four bars around a square, each overlapping the next at a corner. Each
+        // bar is in front of the one after it, and the last is in front of the
+        // first, so there is no order in which they could be painted.
+        let mut scene = Scene::new();
+        scene
+            .add_poly(rect(0., 0., 8., 2., 0.).with_z_gradient(Point::new(1., 0.)))
+            .unwrap();
+        scene
+            .add_poly(rect(6., 0., 8., 8., 0.).with_z_gradient(Point::new(0., 1.)))
+            .unwrap();
+        scene
+            .add_poly(rect(0., 6., 8., 8., 10.).with_z_gradient(Point::new(-1., 0.)))
+            .unwrap();
+        scene.add_poly(rect(0., 0., 2., 8., 5.)).unwrap();
+
+        let visible = scene.visible_lines();
+        let corners = [
+            // Bottom right: the bottom bar is in front of the right bar.
+            (Point::new(7., 2.), Point::new(6., 1.)),
+            // Top right: the right bar is in front of the top bar.
+            (Point::new(6., 7.), Point::new(7., 6.)),
+            // Top left: the top bar is in front of the left bar.
+            (Point::new(1., 6.), Point::new(2., 7.)),
+            // Bottom left: the left bar is in front of the bottom bar.
+            (Point::new(2., 1.), Point::new(1., 2.)),
+        ];
+        for (front, back) in &corners {
+            assert!(on_line(&visible, *front), "{:?} should be visible", front);
+            assert!(!on_line(&visible, *back), "{:?} should be hidden", back);
+        }
+
+        // The outside of the frame is all visible.
+        for point in &[
+            Point::new(4., 0.),
+            Point::new(8., 4.),
+            Point::new(4., 8.),
+            Point::new(0., 4.),
+        ] {
+            assert!(on_line(&visible, *point));
+        }
+    }
+}